@@ -33,6 +33,39 @@ pub fn rect_intersect(x: i32, y: i32, rect: &Rect) -> bool {
     x >= upper_left.0 && y >= upper_left.1 && x <= bottom_right.0 && y <= bottom_right.1
 }
 
+// how many of each kind of pile the board has, which decides how it's laid out
+#[derive(Clone, Copy, PartialEq)]
+pub struct BoardShape {
+    pub columns: u32,
+    pub free_cells: u32,
+    pub foundations: u32,
+}
+
+impl BoardShape {
+    pub fn of(view: &BoardView) -> Self {
+        BoardShape {
+            columns: view.columns.len().try_into().unwrap(),
+            free_cells: view.free_cells.len().try_into().unwrap(),
+            foundations: view.foundations.len().try_into().unwrap(),
+        }
+    }
+
+    // when the free cells & foundations don't fit beside each other over the tableau,
+    // the foundations get a row to themselves above the free cells
+    fn wraps_top_row(&self) -> bool {
+        self.free_cells + self.foundations > self.columns
+    }
+
+    // how many cards wide the board is
+    fn slots(&self) -> u32 {
+        if self.wraps_top_row() {
+            self.columns.max(self.free_cells).max(self.foundations)
+        } else {
+            self.columns
+        }
+    }
+}
+
 // holds info about the proportions of the game field
 struct Dimensions {
     // margin around entire game
@@ -40,8 +73,6 @@ struct Dimensions {
     v_border: u32,
     // vertical offset of free cells from foundations
     free_cell_offset: u32,
-    // margin between free cells and tableau
-    tableau_border: u32,
     // margin between columns
     col_margin: u32,
     // vertical length of covered part of card
@@ -50,6 +81,11 @@ struct Dimensions {
     card_visible: u32,
     // width of cards
     card_width: u32,
+    // how many card widths along the foundations start
+    foundation_slot: u32,
+    // how far down the free cells & the tableau start
+    free_cell_top: u32,
+    tableau_top: u32,
     // height & width of window
     canvas_width: u32,
     canvas_height: u32,
@@ -64,11 +100,15 @@ struct Dimensions {
 
 impl Dimensions {
     // calculates dimensions based on canvas size
-    fn find(columns: u32, canvas_width: u32, canvas_height: u32) -> Dimensions {
+    fn find(shape: BoardShape, canvas_width: u32, canvas_height: u32) -> Dimensions {
+        // the cards shrink to fit however many of them need to go side by side
+        let columns = shape.slots();
+        let foundation_slot = columns - shape.foundations;
         let big_margin = (canvas_width as f64 / 35.0).ceil() as u32;
         let small_margin = big_margin * 3 / 4;
         let h_border = big_margin;
         let v_border = big_margin;
+        // margin between free cells and tableau
         let tableau_border = big_margin;
         let free_cell_offset = small_margin;
         let col_margin = small_margin;
@@ -87,6 +127,13 @@ impl Dimensions {
             let overlap = 0;
             (width, visible, overlap)
         };
+        let card_height = card_visible + card_overlap;
+        let free_cell_top = if shape.wraps_top_row() {
+            v_border + card_height + free_cell_offset
+        } else {
+            v_border + free_cell_offset
+        };
+        let tableau_top = free_cell_top + card_height + tableau_border;
         let corner_point_size = (h_border + free_cell_offset) as u16 / 2;
         let centre_point_size = canvas_height as u16 / 9;
         let card_point_size = card_visible as u16 * 3 / 4;
@@ -95,11 +142,13 @@ impl Dimensions {
             h_border,
             v_border,
             free_cell_offset,
-            tableau_border,
             col_margin,
             card_overlap,
             card_visible,
             card_width,
+            foundation_slot,
+            free_cell_top,
+            tableau_top,
             canvas_width,
             canvas_height,
             card_v_padding: 1,
@@ -114,7 +163,7 @@ impl Dimensions {
     fn get_free_cell(&self, n: u32) -> Rect {
         Rect::new(
             i32::try_from(self.h_border + self.card_width * n + self.col_margin * n).unwrap(),
-            i32::try_from(self.v_border + self.free_cell_offset).unwrap(),
+            i32::try_from(self.free_cell_top).unwrap(),
             self.card_width,
             self.card_visible + self.card_overlap,
         )
@@ -122,9 +171,9 @@ impl Dimensions {
 
     // find rect of the nth foundation
     fn get_foundation(&self, n: u32) -> Rect {
+        let slot = self.foundation_slot + n;
         Rect::new(
-            i32::try_from(self.h_border + self.card_width * (n + 4) + self.col_margin * (n + 4))
-                .unwrap(),
+            i32::try_from(self.h_border + self.card_width * slot + self.col_margin * slot).unwrap(),
             i32::try_from(self.v_border).unwrap(),
             self.card_width,
             self.card_visible + self.card_overlap,
@@ -136,14 +185,7 @@ impl Dimensions {
     fn get_column_card(&self, col: u32, card: u32) -> Rect {
         Rect::new(
             i32::try_from(self.h_border + self.card_width * col + self.col_margin * col).unwrap(),
-            i32::try_from(
-                self.v_border
-                    + self.free_cell_offset
-                    + self.tableau_border
-                    + self.card_overlap
-                    + self.card_visible * (1 + card),
-            )
-            .unwrap(),
+            i32::try_from(self.tableau_top + self.card_visible * card).unwrap(),
             self.card_width,
             self.card_visible + self.card_overlap,
         )
@@ -152,11 +194,7 @@ impl Dimensions {
     // gets the rect representing the entire column.
     // when a held card is dropped, these are used to determine which column it's being dropped on
     fn get_column(&self, col: u32) -> Rect {
-        let top = self.v_border
-            + self.free_cell_offset
-            + self.tableau_border
-            + self.card_overlap
-            + self.card_visible;
+        let top = self.tableau_top;
 
         Rect::new(
             i32::try_from(self.h_border + self.card_width * col + self.col_margin * col).unwrap(),
@@ -243,6 +281,7 @@ struct Colours {
     red_card_colour: Color,
     black_card_colour: Color,
     faint_card_colour: Color,
+    hint_colour: Color,
 }
 
 impl Colours {
@@ -258,6 +297,7 @@ impl Colours {
             status_text_colour: Color::RGB(0xff, 0xff, 0xff),
             victory_text_colour: Color::RGB(0xff, 0xff, 0xff),
             restart_text_colour: Color::RGB(0, 0, 0),
+            hint_colour: Color::RGB(0xff, 0xd0, 0x20),
         }
     }
 }
@@ -270,6 +310,8 @@ pub struct Timings {
     pub new_game_secs: Duration,
     // how long between auto-moves
     pub auto_move_secs: Duration,
+    // how long between moves when sending every card to the foundations at once
+    pub auto_complete_secs: Duration,
 }
 
 impl Timings {
@@ -279,6 +321,7 @@ impl Timings {
             window_size_display_secs: Duration::from_secs(1),
             new_game_secs: Duration::from_secs_f32(2.5),
             auto_move_secs: Duration::from_secs_f32(0.2),
+            auto_complete_secs: Duration::from_secs_f32(0.05),
         }
     }
 }
@@ -286,7 +329,7 @@ impl Timings {
 // Holds all of the configuration info about how the game should
 // be displayed. Fonts, colours, coordinates,
 pub struct UiSettings<'a, 'b> {
-    columns: u32,
+    shape: BoardShape,
     dimensions: Dimensions,
     colours: Colours,
     timings: Timings,
@@ -298,15 +341,15 @@ impl<'a, 'b: 'a> UiSettings<'a, 'b> {
         canvas_width: u32,
         canvas_height: u32,
         ttf_context: &'b Sdl2TtfContext,
+        shape: BoardShape,
     ) -> Result<Self> {
-        let columns = 8;
-        let dimensions = Dimensions::find(columns, canvas_width, canvas_height);
+        let dimensions = Dimensions::find(shape, canvas_width, canvas_height);
         let fonts: Fonts<'a, 'b> = Fonts::load(&dimensions, ttf_context)?;
         let colours = Colours::default();
         let timings = Timings::default();
 
         Ok(UiSettings {
-            columns,
+            shape,
             dimensions,
             timings,
             colours,
@@ -317,11 +360,22 @@ impl<'a, 'b: 'a> UiSettings<'a, 'b> {
     // update all the proportions and font sizes.
     // used when the window size changes
     pub fn update_proportions(&mut self, canvas_width: u32, canvas_height: u32) -> Result<()> {
-        self.dimensions = Dimensions::find(self.columns, canvas_width, canvas_height);
+        self.dimensions = Dimensions::find(self.shape, canvas_width, canvas_height);
         self.fonts = Fonts::load(&self.dimensions, self.fonts.ttf_context)?;
         Ok(())
     }
 
+    // lay the board out again if its shape has changed, like when a game with different
+    // rules is started
+    pub fn fit_to_board(&mut self, view: &BoardView) -> Result<()> {
+        let shape = BoardShape::of(view);
+        if shape != self.shape {
+            self.shape = shape;
+            self.update_proportions(self.dimensions.canvas_width, self.dimensions.canvas_height)?;
+        }
+        Ok(())
+    }
+
     pub fn timings(&self) -> &Timings {
         &self.timings
     }
@@ -347,7 +401,7 @@ pub fn get_card_rects(view: &BoardView, settings: &UiSettings) -> Vec<CardRect>
             board_rects.push(CardRect {
                 card: *card,
                 rect: settings.dimensions.get_foundation(n.try_into().unwrap()),
-                address: CardAddress::Foundation(n.try_into().unwrap()),
+                address: CardAddress::Foundation(card.suit),
                 stack_size: None,
             });
         }
@@ -370,9 +424,9 @@ pub fn get_card_rects(view: &BoardView, settings: &UiSettings) -> Vec<CardRect>
 // get rects representing the areas you can place held cards.
 // by checking which rect the mouse intersects with, you can find the address
 // the player is placing the card at
-pub fn get_placement_zones(settings: &UiSettings) -> Vec<(CardAddress, Rect)> {
+pub fn get_placement_zones(view: &BoardView, settings: &UiSettings) -> Vec<(CardAddress, Rect)> {
     let mut zones = Vec::with_capacity(16);
-    for n in 0..4 {
+    for n in 0..view.free_cells.len() {
         let card = settings.dimensions.get_free_cell(n.try_into().unwrap());
         let zone = Rect::new(
             card.x() - i32::try_from(settings.dimensions.col_margin).unwrap() / 2,
@@ -382,7 +436,7 @@ pub fn get_placement_zones(settings: &UiSettings) -> Vec<(CardAddress, Rect)> {
         );
         zones.push((CardAddress::FreeCell(n), zone));
     }
-    for n in 0..4 {
+    for (n, foundation) in view.foundations.iter().enumerate() {
         let card = settings.dimensions.get_foundation(n.try_into().unwrap());
         let zone = Rect::new(
             card.x() - i32::try_from(settings.dimensions.col_margin).unwrap() / 2,
@@ -390,9 +444,9 @@ pub fn get_placement_zones(settings: &UiSettings) -> Vec<(CardAddress, Rect)> {
             card.width() + settings.dimensions.col_margin,
             card.height(),
         );
-        zones.push((CardAddress::Foundation(n.try_into().unwrap()), zone));
+        zones.push((CardAddress::Foundation(foundation.suit), zone));
     }
-    for n in 0..8 {
+    for n in 0..view.columns.len() {
         let card = settings.dimensions.get_column(n.try_into().unwrap());
         let zone = Rect::new(
             card.x() - i32::try_from(settings.dimensions.col_margin).unwrap() / 2,
@@ -435,24 +489,28 @@ pub fn draw_background(canvas: &mut Canvas<sdl2::video::Window>, settings: &UiSe
     canvas.set_draw_color(old_colour);
 }
 
-// draw the background & all the cards
+// draw the background & all the cards.
+// if there's a hint, the cards it moves and the place it moves them to are outlined
 pub fn draw_board<'a>(
     canvas: &mut Canvas<Surface<'a>>,
     view: &BoardView,
     settings: &UiSettings,
     mouse: (i32, i32),
+    hint: Option<&Move>,
 ) -> Result<()> {
     let old_colour = canvas.draw_color();
 
     canvas.set_draw_color(settings.colours.faint_card_colour);
-    for n in 0..4 {
-        let rect = settings.dimensions.get_free_cell(n);
+    for n in 0..view.free_cells.len() {
+        let rect = settings.dimensions.get_free_cell(n.try_into().unwrap());
         canvas
             .fill_rect(rect)
             .map_err(|e| anyhow!("filling rect: {}", e))?;
     }
-    for n in 0..8 {
-        let rect = settings.dimensions.get_column_card(n, 0);
+    for n in 0..view.columns.len() {
+        let rect = settings
+            .dimensions
+            .get_column_card(n.try_into().unwrap(), 0);
         canvas
             .fill_rect(rect)
             .map_err(|e| anyhow!("filling rect: {}", e))?;
@@ -461,6 +519,9 @@ pub fn draw_board<'a>(
     for card_rect in get_card_rects(view, settings) {
         draw_card(canvas, settings, card_rect.card, card_rect.rect)?;
     }
+    if let Some(hint) = hint {
+        draw_hint(canvas, view, settings, hint)?;
+    }
     for (card, rect) in get_floating_rects(view, settings, mouse.0, mouse.1) {
         draw_card(canvas, settings, card, rect)?;
     }
@@ -469,6 +530,46 @@ pub fn draw_board<'a>(
     Ok(())
 }
 
+// outline the cards being moved by a hint, and the zone they should be dropped in
+fn draw_hint<'a>(
+    canvas: &mut Canvas<Surface<'a>>,
+    view: &BoardView,
+    settings: &UiSettings,
+    hint: &Move,
+) -> Result<()> {
+    // the moved cards are the top "count" cards at the source address
+    let source = get_card_rects(view, settings)
+        .into_iter()
+        .filter(|c| c.address == hint.from && c.stack_size.unwrap_or(1) <= hint.count)
+        .map(|c| c.rect)
+        .fold(None, |acc: Option<Rect>, rect| {
+            Some(acc.map_or(rect, |acc| acc.union(rect)))
+        });
+    let destination = get_placement_zones(view, settings)
+        .into_iter()
+        .find(|(address, _)| *address == hint.to)
+        .map(|(_, rect)| rect);
+
+    canvas.set_draw_color(settings.colours.hint_colour);
+    for rect in source.iter().chain(destination.iter()) {
+        // draw a few nested outlines to make the border thicker
+        for n in 0..3 {
+            if rect.width() > 2 * n && rect.height() > 2 * n {
+                let outline = Rect::new(
+                    rect.x() + i32::try_from(n).unwrap(),
+                    rect.y() + i32::try_from(n).unwrap(),
+                    rect.width() - 2 * n,
+                    rect.height() - 2 * n,
+                );
+                canvas
+                    .draw_rect(outline)
+                    .map_err(|e| anyhow!("drawing rect: {}", e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
 // draws a card to the screen
 fn draw_card<'a>(
     canvas: &mut Canvas<Surface<'a>>,
@@ -583,6 +684,55 @@ pub fn draw_victory_text<'a>(
     )
 }
 
+// Draw text in the middle of the window in the "victory" style,
+// with a smaller line of advice underneath it
+pub fn draw_dead_end_text(
+    ui_settings: &UiSettings,
+    canvas: &mut Canvas<Surface>,
+    text: &str,
+    advice: &str,
+) -> Result<()> {
+    let title = create_text_surface(
+        &ui_settings.fonts.centre_font,
+        ui_settings.colours.victory_text_colour,
+        text,
+        Some(ui_settings.colours.background),
+    )?;
+    let advice = create_text_surface(
+        &ui_settings.fonts.corner_font,
+        ui_settings.colours.victory_text_colour,
+        advice,
+        Some(ui_settings.colours.background),
+    )?;
+    // centre both lines together, with the advice right under the title
+    let height = title.height() + advice.height();
+    let width = title.width().max(advice.width());
+    if let Some(rect) = ui_settings
+        .dimensions
+        .centre_rect(&Rect::new(0, 0, width, height))
+    {
+        let title_rect = Rect::new(
+            rect.x() + i32::try_from((width - title.width()) / 2).unwrap(),
+            rect.y(),
+            title.width(),
+            title.height(),
+        );
+        let advice_rect = Rect::new(
+            rect.x() + i32::try_from((width - advice.width()) / 2).unwrap(),
+            rect.y() + i32::try_from(title.height()).unwrap(),
+            advice.width(),
+            advice.height(),
+        );
+        title
+            .blit(None, canvas.surface_mut(), title_rect)
+            .map_err(|s| anyhow!("rendering text to surface: {}", s))?;
+        advice
+            .blit(None, canvas.surface_mut(), advice_rect)
+            .map_err(|s| anyhow!("rendering text to surface: {}", s))?;
+    }
+    Ok(())
+}
+
 // Draw text in the middle of the window in the "reset" style
 pub fn draw_reset_text<'a>(
     ui_settings: &UiSettings,
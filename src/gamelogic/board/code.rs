@@ -0,0 +1,210 @@
+// a compact encoding of a position, for sharing positions & keeping them small.
+// a position is written as a run of 6-bit symbols: a version, the rules, the rank on each
+// foundation, what's in each free cell, the cards in each column followed by a 0, and the
+// held cards with the column they came from. a card is its suit in the top two bits and
+// its rank in the bottom four, so it's never 0.
+// as bytes, the symbols are packed four to every three bytes. as text, the bytes are
+// written in url-safe base 64, which comes out to one character per symbol
+
+use super::*;
+
+const VERSION: u8 = 1;
+
+// bits in the symbol for the parts of the rules that are on or off
+const SAME_SUIT: u8 = 1;
+const KINGS_ONLY: u8 = 2;
+const LIMITED_STACKS: u8 = 4;
+
+const BASE_64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+impl Board {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let state = &self.state;
+        let rules = &state.rules;
+        let mut flags = 0;
+        if rules.building == Building::SameSuit {
+            flags |= SAME_SUIT;
+        }
+        if rules.empty_columns == EmptyColumns::KingsOnly {
+            flags |= KINGS_ONLY;
+        }
+        if rules.limited_stacks {
+            flags |= LIMITED_STACKS;
+        }
+        let mut symbols = vec![
+            VERSION,
+            rules.columns as u8,
+            rules.free_cells as u8,
+            rules.dealt_to_free_cells as u8,
+            flags,
+            rules.decks as u8,
+        ];
+        symbols.extend(state.foundations.iter().map(|card| card.rank));
+        symbols.extend(
+            state
+                .free_cells
+                .iter()
+                .map(|cell| cell.map_or(0, card_symbol)),
+        );
+        for column in &state.columns {
+            symbols.extend(column.iter().copied().map(card_symbol));
+            symbols.push(0);
+        }
+        symbols.push(state.stack_source.map_or(0, |n| n as u8 + 1));
+        let held = state
+            .floating
+            .iter()
+            .chain(state.floating_stack.iter().flatten());
+        symbols.extend(held.copied().map(card_symbol));
+        symbols.push(0);
+        pack(&symbols)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, ParseError> {
+        let mut symbols = unpack(bytes).into_iter();
+        let mut next = || symbols.next().ok_or_else(|| bad(CodeError::TooShort));
+
+        if next()? != VERSION {
+            return Err(bad(CodeError::Version));
+        }
+        let (columns, free_cells, dealt_to_free_cells, flags, decks) =
+            (next()?, next()?, next()?, next()?, next()?);
+        let rules = Rules {
+            columns: columns.into(),
+            free_cells: free_cells.into(),
+            dealt_to_free_cells: dealt_to_free_cells.into(),
+            building: if flags & SAME_SUIT != 0 {
+                Building::SameSuit
+            } else {
+                Building::AlternateColours
+            },
+            empty_columns: if flags & KINGS_ONLY != 0 {
+                EmptyColumns::KingsOnly
+            } else {
+                EmptyColumns::AnyCard
+            },
+            limited_stacks: flags & LIMITED_STACKS != 0,
+            decks: decks.into(),
+        };
+        rules
+            .check()
+            .map_err(|reason| bad(CodeError::Rules { reason }))?;
+
+        let mut state = Board::empty(&rules).state;
+        for foundation in state.foundations.iter_mut() {
+            foundation.rank = next()?;
+        }
+        for cell in state.free_cells.iter_mut() {
+            let symbol = next()?;
+            *cell = if symbol == 0 {
+                None
+            } else {
+                Some(symbol_card(symbol))
+            };
+        }
+        for _ in 0..rules.columns {
+            let mut column = Vec::new();
+            loop {
+                match next()? {
+                    0 => break,
+                    symbol => column.push(symbol_card(symbol)),
+                }
+            }
+            state.columns.push(Arc::new(column));
+        }
+        state.stack_source = match next()? {
+            0 => None,
+            n => Some(usize::from(n) - 1),
+        };
+        let mut held = Vec::new();
+        loop {
+            match next()? {
+                0 => break,
+                symbol => held.push(symbol_card(symbol)),
+            }
+        }
+        if held.len() == 1 {
+            state.floating = held.pop();
+        } else if !held.is_empty() {
+            state.floating_stack = Some(held);
+        }
+        // anything left over is padding from packing the symbols into bytes
+        if symbols.any(|symbol| symbol != 0) {
+            return Err(bad(CodeError::TooLong));
+        }
+
+        let board: Board = state.into();
+        board.validate()?;
+        Ok(board)
+    }
+
+    // the position as a short piece of text, which can be read back with from_code
+    pub fn to_code(&self) -> String {
+        let bytes = self.to_bytes();
+        let mut code = String::new();
+        for chunk in bytes.chunks(3) {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (n, &byte)| {
+                bits | u32::from(byte) << (16 - 8 * n)
+            });
+            for n in 0..=chunk.len() {
+                code.push(BASE_64[(bits >> (18 - 6 * n) & 63) as usize] as char);
+            }
+        }
+        code
+    }
+
+    pub fn from_code(code: &str) -> std::result::Result<Self, ParseError> {
+        let digits = code
+            .trim()
+            .bytes()
+            .map(|c| BASE_64.iter().position(|&d| d == c).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| bad(CodeError::Character))?;
+        Board::from_bytes(&pack(&digits))
+    }
+}
+
+fn bad(reason: CodeError) -> ParseError {
+    ParseError::BadCode { reason }
+}
+
+fn card_symbol(card: Card) -> u8 {
+    (usize::from(card.suit) as u8) << 4 | card.rank
+}
+
+fn symbol_card(symbol: u8) -> Card {
+    Card::new(symbol & 15, usize::from(symbol >> 4).try_into().unwrap())
+}
+
+// pack 6-bit symbols into bytes, with the last byte padded out with zeros
+fn pack(symbols: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(symbols.len() * 3 / 4 + 1);
+    let (mut bits, mut count) = (0u32, 0);
+    for &symbol in symbols {
+        bits = bits << 6 | u32::from(symbol & 63);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    if count > 0 {
+        bytes.push((bits << (8 - count)) as u8);
+    }
+    bytes
+}
+
+// the 6-bit symbols in some bytes, including any padding at the end
+fn unpack(bytes: &[u8]) -> Vec<u8> {
+    let mut symbols = Vec::with_capacity(bytes.len() * 4 / 3);
+    let (mut bits, mut count) = (0u32, 0);
+    for &byte in bytes {
+        bits = bits << 8 | u32::from(byte);
+        count += 8;
+        while count >= 6 {
+            count -= 6;
+            symbols.push((bits >> count) as u8 & 63);
+        }
+    }
+    symbols
+}
@@ -0,0 +1,89 @@
+// zobrist-style hashes of positions, for spotting a position that's come up before
+// without comparing whole boards.
+// every card in every place gets its own number, and a position's hash combines the
+// numbers for where its cards are. the numbers come from mixing the place & the card
+// rather than from a random table, so hashes are the same on every run and platform
+
+use super::*;
+
+// the kinds of place a card can be in
+const COLUMN: u64 = 1;
+const FREE_CELL: u64 = 2;
+const FOUNDATION: u64 = 3;
+const FLOATING: u64 = 4;
+
+impl Board {
+    // a hash of exactly where every card is
+    pub fn position_hash(&self) -> u64 {
+        let state = &self.state;
+        let mut hash = 0;
+        for (n, column) in state.columns.iter().enumerate() {
+            for (depth, &card) in column.iter().enumerate() {
+                hash ^= feature(place(COLUMN, n, depth), card);
+            }
+        }
+        for (n, cell) in state.free_cells.iter().enumerate() {
+            if let Some(card) = *cell {
+                hash ^= feature(place(FREE_CELL, n, 0), card);
+            }
+        }
+        for (n, &card) in state.foundations.iter().enumerate() {
+            hash ^= feature(place(FOUNDATION, n, 0), card);
+        }
+        hash ^ floating_hash(state)
+    }
+
+    // a hash that's the same for positions which only differ by the order of their
+    // free cells, of their columns, or of foundations of the same suit. two positions
+    // like that play out the same way, so a search only needs to look at one of them
+    pub fn canonical_hash(&self) -> u64 {
+        let state = &self.state;
+        // adding is used to combine things whose order doesn't matter, since unlike xor
+        // it doesn't cancel out a card that's there twice
+        let mut hash: u64 = 0;
+        for column in state.columns.iter() {
+            let mut column_hash = 0;
+            for (depth, &card) in column.iter().enumerate() {
+                column_hash ^= feature(place(COLUMN, 0, depth), card);
+            }
+            hash = hash.wrapping_add(mix(column_hash));
+        }
+        for card in state.free_cells.iter().flatten() {
+            hash = hash.wrapping_add(feature(place(FREE_CELL, 0, 0), *card));
+        }
+        for &card in state.foundations.iter() {
+            hash = hash.wrapping_add(feature(place(FOUNDATION, 0, 0), card));
+        }
+        hash ^ floating_hash(state)
+    }
+}
+
+// the cards being held, which are always in order
+fn floating_hash(state: &State) -> u64 {
+    state
+        .floating
+        .iter()
+        .chain(state.floating_stack.iter().flatten())
+        .enumerate()
+        .fold(0, |hash, (n, &card)| {
+            hash ^ feature(place(FLOATING, n, 0), card)
+        })
+}
+
+fn place(kind: u64, index: usize, depth: usize) -> u64 {
+    kind << 48 | (index as u64) << 32 | depth as u64
+}
+
+// the number for a card being in a place
+fn feature(place: u64, card: Card) -> u64 {
+    let card = (usize::from(card.suit) as u64) << 8 | card.rank as u64;
+    mix(mix(place) ^ card)
+}
+
+// the splitmix64 finalizer, which scrambles every bit of its input into every bit of its output
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -1,5 +1,6 @@
 use std::convert::TryInto;
 use std::fmt;
+use std::sync::Arc;
 
 use rand::seq::SliceRandom;
 use rand_chacha::rand_core::SeedableRng;
@@ -8,7 +9,14 @@ use serde::{Deserialize, Serialize};
 
 use super::card::*;
 use super::error::*;
+use super::rules::*;
 
+// columns are shared between boards until one of them changes, so that boards are cheap to copy
+type CardColumn = Arc<Vec<Card>>;
+
+mod code;
+mod hash;
+mod parse;
 #[cfg(test)]
 mod test;
 
@@ -28,6 +36,12 @@ struct State {
     free_cells: Vec<Option<Card>>,
     floating: Option<Card>,
     floating_stack: Option<Vec<Card>>,
+    // the column the floating stack was picked up from
+    #[serde(default)]
+    stack_source: Option<usize>,
+    // older saves were all made playing plain FreeCell
+    #[serde(default)]
+    rules: Rules,
 }
 
 impl From<State> for Board {
@@ -42,7 +56,7 @@ impl From<State> for Board {
             None
         };
         let view = BoardView {
-            columns: state.columns.clone(),
+            columns: state.columns.iter().map(|c| c.to_vec()).collect(),
             foundations: state.foundations.clone(),
             free_cells: state.free_cells.clone(),
             floating,
@@ -51,38 +65,140 @@ impl From<State> for Board {
     }
 }
 
+// which cards get moved to the foundations without the player asking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoMovePolicy {
+    // nothing moves on its own
+    Off,
+    // only cards that nothing left in play could still stack on
+    Safe,
+    // every card that can go to a foundation
+    Aggressive,
+}
+
+// the policies in the order they're cycled through in-game
+pub const AUTO_MOVE_POLICIES: &[AutoMovePolicy] = &[
+    AutoMovePolicy::Off,
+    AutoMovePolicy::Safe,
+    AutoMovePolicy::Aggressive,
+];
+
+impl AutoMovePolicy {
+    pub fn next(self) -> Self {
+        let index = AUTO_MOVE_POLICIES.iter().position(|&p| p == self).unwrap();
+        AUTO_MOVE_POLICIES[(index + 1) % AUTO_MOVE_POLICIES.len()]
+    }
+}
+
+impl std::str::FromStr for AutoMovePolicy {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        AUTO_MOVE_POLICIES
+            .iter()
+            .find(|p| p.to_string() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "unknown auto-move policy {:?}, expected off, safe or aggressive",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for AutoMovePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoMovePolicy::Off => write!(f, "off"),
+            AutoMovePolicy::Safe => write!(f, "safe"),
+            AutoMovePolicy::Aggressive => write!(f, "aggressive"),
+        }
+    }
+}
+
 impl Board {
-    fn empty() -> Self {
+    fn empty(rules: &Rules) -> Self {
         State {
             columns: Vec::new(),
-            foundations: (0..4)
-                .map(|n: usize| Card::new(0, n.try_into().unwrap()))
+            // with more than one deck, the foundations go round the suits again
+            foundations: (0..rules.foundations())
+                .map(|n: usize| Card::new(0, (n % 4).try_into().unwrap()))
                 .collect(),
-            free_cells: vec![None; 4],
+            free_cells: vec![None; rules.free_cells],
             floating: None,
             floating_stack: None,
+            stack_source: None,
+            rules: *rules,
         }
         .into()
     }
 
     // shuffle & create a new game
-    pub fn new_game(seed: u64) -> Self {
-        let mut spread = Board::empty().state;
-        let mut deck = Vec::with_capacity(52);
-        for &suit in &[Suit::Clubs, Suit::Diamonds, Suit::Spades, Suit::Hearts] {
-            for rank in 1..=13 {
-                deck.push(Card::new(rank, suit));
+    pub fn new_game(seed: u64, rules: &Rules) -> std::result::Result<Self, BoardError> {
+        check_rules(rules)?;
+        let mut spread = Board::empty(rules).state;
+        let mut deck = Vec::with_capacity(52 * rules.decks);
+        for _ in 0..rules.decks {
+            for &suit in &[Suit::Clubs, Suit::Diamonds, Suit::Spades, Suit::Hearts] {
+                for rank in 1..=13 {
+                    deck.push(Card::new(rank, suit));
+                }
             }
         }
         let mut rng = ChaCha12Rng::seed_from_u64(seed);
         deck.shuffle(&mut rng);
-        for &n in &[7, 7, 7, 7, 6, 6, 6] {
-            let (new, remainder) = deck.split_at(n);
-            spread.columns.push(Vec::from(new));
+        // deal the columns out one at a time, with the leftover cards going to the first
+        // columns, then put the last few cards in the free cells
+        let in_columns = deck.len() - rules.dealt_to_free_cells;
+        for n in 0..rules.columns {
+            let size = in_columns / rules.columns + usize::from(n < in_columns % rules.columns);
+            let (new, remainder) = deck.split_at(size);
+            spread.columns.push(Arc::new(Vec::from(new)));
             deck = Vec::from(remainder);
         }
-        spread.columns.push(deck);
-        spread.into()
+        for (cell, card) in spread.free_cells.iter_mut().zip(deck) {
+            *cell = Some(card);
+        }
+        Ok(spread.into())
+    }
+
+    // create the same game as the classic Microsoft FreeCell deal with this number.
+    // under other rules, the cards are shuffled the same way and dealt out to fit
+    pub fn new_microsoft_game(
+        deal_number: u32,
+        rules: &Rules,
+    ) -> std::result::Result<Self, BoardError> {
+        check_rules(rules)?;
+        let mut spread = Board::empty(rules).state;
+        // the cards are numbered so that card n has rank n / 4 and suit n % 4,
+        // with any extra decks numbered after the first
+        let mut deck: Vec<Card> = (0..52 * rules.decks)
+            .map(|n: usize| Card::new((n % 52 / 4 + 1) as u8, (n % 4).try_into().unwrap()))
+            .collect();
+        // Microsoft's C runtime rand(), seeded with the deal number
+        let mut state = deal_number;
+        let mut rand = || {
+            state = state.wrapping_mul(214_013).wrapping_add(2_531_011) & 0x7fff_ffff;
+            (state >> 16) as usize
+        };
+        // take random cards out of the deck, filling the hole with the card from the end
+        let mut dealt = Vec::with_capacity(deck.len());
+        while !deck.is_empty() {
+            let n = rand() % deck.len();
+            dealt.push(deck.swap_remove(n));
+        }
+        // deal the cards out a row at a time, then put the last few in the free cells
+        let in_columns = dealt.len() - rules.dealt_to_free_cells;
+        let for_free_cells = dealt.split_off(in_columns);
+        let mut columns = vec![Vec::new(); rules.columns];
+        for (n, card) in dealt.into_iter().enumerate() {
+            columns[n % rules.columns].push(card);
+        }
+        spread.columns = columns.into_iter().map(Arc::new).collect();
+        for (cell, card) in spread.free_cells.iter_mut().zip(for_free_cells) {
+            *cell = Some(card);
+        }
+        Ok(spread.into())
     }
 
     // pick up a card from a position
@@ -91,7 +207,7 @@ impl Board {
         if self.state.floating != None || self.state.floating_stack != None {
             return Err(MoveError::CannotPickUp {
                 from: address,
-                reason: REASON_ALREADY_HOLDING.to_string(),
+                reason: MoveRejection::AlreadyHolding,
             });
         }
         match address {
@@ -99,13 +215,13 @@ impl Board {
             CardAddress::Column(i) => {
                 let mut result = self.state.clone();
                 if let Some(column) = &mut result.columns.get_mut(i) {
-                    if let Some(card) = column.pop() {
+                    if let Some(card) = Arc::make_mut(column).pop() {
                         result.floating = Some(card);
                         Ok(result.into())
                     } else {
                         Err(MoveError::CannotPickUp {
                             from: address,
-                            reason: REASON_EMPTY_ADDRESS.to_string(),
+                            reason: MoveRejection::EmptyAddress,
                         })
                     }
                 } else {
@@ -115,7 +231,7 @@ impl Board {
             // it's illegal to pick up from a foundation
             CardAddress::Foundation(s) => Err(MoveError::CannotPickUp {
                 from: CardAddress::Foundation(s),
-                reason: REASON_MOVE_FOUNDATION.to_string(),
+                reason: MoveRejection::OffFoundation,
             }),
             // pick up a card from a free cell
             CardAddress::FreeCell(i) => {
@@ -128,7 +244,7 @@ impl Board {
                     } else {
                         Err(MoveError::CannotPickUp {
                             from: address,
-                            reason: REASON_EMPTY_ADDRESS.to_string(),
+                            reason: MoveRejection::EmptyAddress,
                         })
                     }
                 } else {
@@ -145,14 +261,14 @@ impl Board {
             if self.state.floating != None || self.state.floating_stack != None {
                 return Err(MoveError::CannotPickUp {
                     from: address,
-                    reason: REASON_ALREADY_HOLDING.to_string(),
+                    reason: MoveRejection::AlreadyHolding,
                 });
             }
-            let max_possible_stack_size = self.max_stack_size();
+            let max_possible_stack_size = self.max_stack_size(None);
             match number_of_cards {
                 0 => Err(MoveError::CannotPickUp {
                     from: address,
-                    reason: REASON_EMPTY_STACK.to_string(),
+                    reason: MoveRejection::EmptyStack,
                 }),
                 1 => self.pick_up_card(address),
                 _ => {
@@ -163,29 +279,35 @@ impl Board {
                             if number_of_cards <= max_possible_stack_size {
                                 // check if the cards in the column are legally allowed to stack
                                 let it = column.iter().rev();
-                                for pair in it.clone().take(number_of_cards - 1).zip(it.skip(1)) {
-                                    if !pair.0.stacks_on(pair.1) {
+                                let pairs = it.clone().take(number_of_cards - 1).zip(it.skip(1));
+                                for (depth, pair) in pairs.enumerate() {
+                                    if !pair.0.stacks_on(pair.1, &self.state.rules) {
                                         return Err(MoveError::CannotPickUp {
                                             from: address,
-                                            reason: REASON_UNSOUND_STACK.to_string(),
+                                            reason: MoveRejection::UnsoundStack {
+                                                position: depth + 1,
+                                            },
                                         });
                                     }
                                 }
                                 // actually pick up the cards
-                                let floating_stack =
-                                    column.split_off(column.len() - number_of_cards);
+                                let remaining = column.len() - number_of_cards;
+                                let floating_stack = Arc::make_mut(column).split_off(remaining);
                                 result.floating_stack = Some(floating_stack);
+                                result.stack_source = Some(column_number);
                                 Ok(result.into())
                             } else {
                                 Err(MoveError::CannotPickUp {
                                     from: address,
-                                    reason: REASON_STACK_TOO_LARGE.to_string(),
+                                    reason: self.stack_too_large(None),
                                 })
                             }
                         } else {
                             Err(MoveError::CannotPickUp {
                                 from: address,
-                                reason: REASON_STACK_LARGER_THAN_COLUMN.to_string(),
+                                reason: MoveRejection::StackLargerThanColumn {
+                                    column_size: column.len(),
+                                },
                             })
                         }
                     } else {
@@ -196,7 +318,7 @@ impl Board {
         } else {
             Err(MoveError::CannotPickUp {
                 from: address,
-                reason: REASON_CAN_ONLY_GET_STACK_FROM_COLUMN.to_string(),
+                reason: MoveRejection::StackOnlyFromColumn,
             })
         }
     }
@@ -209,34 +331,39 @@ impl Board {
                 if let Some(column) = &mut result.columns.get_mut(i) {
                     if let Some(card) = result.floating {
                         // try to place a single card onto a column
-                        if column.is_empty() || card.stacks_on(column.last().unwrap()) {
-                            column.push(card);
-                            result.floating = None;
-                            Ok(result.into())
-                        } else {
+                        if let Err(reason) = self.column_accepts(card, column) {
                             Err(MoveError::CannotPlace {
                                 to: address,
-                                reason: REASON_DOES_NOT_FIT.to_string(),
+                                reason,
                             })
+                        } else {
+                            Arc::make_mut(column).push(card);
+                            result.floating = None;
+                            Ok(result.into())
                         }
                     } else if let Some(cards) = &mut result.floating_stack {
                         // try to place a stack of cards onto a column
-                        if column.is_empty()
-                            || cards.first().unwrap().stacks_on(column.last().unwrap())
-                        {
-                            column.append(cards);
-                            result.floating_stack = None;
-                            Ok(result.into())
-                        } else {
+                        let card = *cards.first().unwrap();
+                        if cards.len() > self.max_stack_size(Some(i)) {
                             Err(MoveError::CannotPlace {
                                 to: address,
-                                reason: REASON_DOES_NOT_FIT.to_string(),
+                                reason: self.stack_too_large(Some(i)),
                             })
+                        } else if let Err(reason) = self.column_accepts(card, column) {
+                            Err(MoveError::CannotPlace {
+                                to: address,
+                                reason,
+                            })
+                        } else {
+                            Arc::make_mut(column).append(cards);
+                            result.floating_stack = None;
+                            result.stack_source = None;
+                            Ok(result.into())
                         }
                     } else {
                         Err(MoveError::CannotPlace {
                             to: address,
-                            reason: REASON_NO_CARDS_HELD.to_string(),
+                            reason: MoveRejection::NoCardsHeld,
                         })
                     }
                 } else {
@@ -245,23 +372,33 @@ impl Board {
             }
 
             CardAddress::Foundation(s) => {
-                // try to move a card to a foundation
-                if let Some(foundation) = result.foundations.get_mut(usize::from(s)) {
+                // try to move a card to a foundation.
+                // with more than one deck, it goes on whichever of its suit's foundations it fits
+                let suit_foundations: Vec<usize> = (0..result.foundations.len())
+                    .filter(|&n| result.foundations[n].suit == s)
+                    .collect();
+                if let Some(&first) = suit_foundations.first() {
                     if let Some(card) = result.floating {
-                        if card.fits_on_foundation(foundation) {
-                            *foundation = card;
+                        let fits = suit_foundations
+                            .into_iter()
+                            .find(|&n| card.fits_on_foundation(&result.foundations[n]));
+                        if let Some(n) = fits {
+                            result.foundations[n] = card;
                             result.floating = None;
                             Ok(result.into())
                         } else {
                             Err(MoveError::CannotPlace {
                                 to: CardAddress::Foundation(s),
-                                reason: REASON_DOES_NOT_FIT.to_string(),
+                                reason: MoveRejection::DoesNotFit {
+                                    card,
+                                    onto: result.foundations[first],
+                                },
                             })
                         }
                     } else {
                         Err(MoveError::CannotPlace {
                             to: address,
-                            reason: REASON_NO_CARDS_HELD.to_string(),
+                            reason: self.nothing_single_held(),
                         })
                     }
                 } else {
@@ -280,13 +417,13 @@ impl Board {
                         } else {
                             Err(MoveError::CannotPlace {
                                 to: CardAddress::FreeCell(i),
-                                reason: REASON_DOES_NOT_FIT.to_string(),
+                                reason: self.nothing_single_held(),
                             })
                         }
                     } else {
                         Err(MoveError::CannotPlace {
                             to: address,
-                            reason: REASON_DOES_NOT_FIT.to_string(),
+                            reason: MoveRejection::CellOccupied,
                         })
                     }
                 } else {
@@ -296,51 +433,360 @@ impl Board {
         }
     }
 
+    // make a whole move at once, as if the cards were picked up and then placed
+    pub fn apply(&self, mv: &Move) -> Result<Self> {
+        let picked_up = if mv.count == 1 {
+            self.pick_up_card(mv.from)?
+        } else {
+            self.pick_up_stack(mv.from, mv.count)?
+        };
+        picked_up.place(mv.to)
+    }
+
+    // true if the move can be made from this position
+    pub fn is_legal(&self, mv: &Move) -> bool {
+        self.apply(mv).is_ok()
+    }
+
+    // every move that can be made from this position: stacks of cards between columns,
+    // single cards to & from free cells, and single cards onto the foundations.
+    // moving a card from one free cell to another is left out, since it changes nothing
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = self.candidate_moves();
+        moves.retain(|mv| self.is_legal(mv));
+        moves
+    }
+
+    // every move that might be legal from this position, which are checked by trying them
+    fn candidate_moves(&self) -> Vec<Move> {
+        let mut candidates = Vec::new();
+        for (n, cell) in self.state.free_cells.iter().enumerate() {
+            if let Some(card) = cell {
+                let from = CardAddress::FreeCell(n);
+                candidates.push(Move {
+                    from,
+                    to: CardAddress::Foundation(card.suit),
+                    count: 1,
+                });
+                for i in 0..self.state.columns.len() {
+                    candidates.push(Move {
+                        from,
+                        to: CardAddress::Column(i),
+                        count: 1,
+                    });
+                }
+            }
+        }
+        for (i, column) in self.state.columns.iter().enumerate() {
+            if let Some(card) = column.last() {
+                let from = CardAddress::Column(i);
+                candidates.push(Move {
+                    from,
+                    to: CardAddress::Foundation(card.suit),
+                    count: 1,
+                });
+                for n in 0..self.state.free_cells.len() {
+                    candidates.push(Move {
+                        from,
+                        to: CardAddress::FreeCell(n),
+                        count: 1,
+                    });
+                }
+                // only stacks where every card stacks on the one below can move together
+                let mut stack_size = 1;
+                for pair in column.iter().rev().zip(column.iter().rev().skip(1)) {
+                    if !pair.0.stacks_on(pair.1, &self.state.rules) {
+                        break;
+                    }
+                    stack_size += 1;
+                }
+                for count in 1..=stack_size {
+                    for j in (0..self.state.columns.len()).filter(|&j| j != i) {
+                        candidates.push(Move {
+                            from,
+                            to: CardAddress::Column(j),
+                            count,
+                        });
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    // true if no legal move would change anything, because the only moves left
+    // shift a whole column into an empty column. false while holding cards
+    pub fn is_stuck(&self) -> bool {
+        !self.has_floating()
+            && self.legal_moves().iter().all(|mv| match (mv.from, mv.to) {
+                (CardAddress::Column(from), CardAddress::Column(to)) => {
+                    self.state.columns[to].is_empty() && mv.count == self.state.columns[from].len()
+                }
+                _ => false,
+            })
+    }
+
+    // the single move that leads from this position to another one, if there is one.
+    // found by looking at what changed, rather than by trying every legal move
+    pub fn move_to(&self, other: &Board) -> Option<Move> {
+        let (before, after) = (&self.state, &other.state);
+        if self.has_floating()
+            || other.has_floating()
+            || before.columns.len() != after.columns.len()
+            || before.free_cells.len() != after.free_cells.len()
+            || before.foundations.len() != after.foundations.len()
+        {
+            return None;
+        }
+        let (mut from, mut to, mut count) = (None, None, 1);
+        for (n, (a, b)) in before.columns.iter().zip(&after.columns).enumerate() {
+            if a.len() > b.len() {
+                from = Some(CardAddress::Column(n));
+                count = a.len() - b.len();
+            } else if a.len() < b.len() {
+                to = Some(CardAddress::Column(n));
+            }
+        }
+        for (n, (a, b)) in before.free_cells.iter().zip(&after.free_cells).enumerate() {
+            match (a, b) {
+                (Some(_), None) => from = Some(CardAddress::FreeCell(n)),
+                (None, Some(_)) => to = Some(CardAddress::FreeCell(n)),
+                _ => {}
+            }
+        }
+        for (a, b) in before.foundations.iter().zip(&after.foundations) {
+            if a.rank < b.rank {
+                to = Some(CardAddress::Foundation(b.suit));
+            }
+        }
+        let mv = Move {
+            from: from?,
+            to: to?,
+            count,
+        };
+        // anything else that changed would make this the wrong move
+        match self.apply(&mv) {
+            Ok(board) if &board == other => Some(mv),
+            _ => None,
+        }
+    }
+
+    // every legal move, along with the position it leads to.
+    // each move is only made once, to both check it and get its position
+    pub fn next_positions(&self) -> Vec<(Move, Board)> {
+        self.candidate_moves()
+            .into_iter()
+            .filter_map(|mv| Some((mv, self.apply(&mv).ok()?)))
+            .collect()
+    }
+
     // get a look at the state of the board
     pub fn view(&self) -> &BoardView {
         &self.view
     }
 
+    // the rules the game is being played under
+    pub fn rules(&self) -> &Rules {
+        &self.state.rules
+    }
+
     // true if the player is holding cards
     pub fn has_floating(&self) -> bool {
         self.state.floating.is_some() || self.state.floating_stack.is_some()
     }
 
-    // find the max number of cards the player can pick up as a stack.
-    // not strictly the max cards the player can move at once, but the max
-    // number they can pick up at once without restricting where they can place them.
-    fn max_stack_size(&self) -> usize {
+    // true if every card left can go to the foundations without any decisions, which is
+    // the case once no card in the columns is covering a lower one. the lowest card left
+    // is then always free, and it's always the next one its foundation needs
+    pub fn can_auto_complete(&self) -> bool {
+        !self.has_floating()
+            && self
+                .state
+                .columns
+                .iter()
+                .all(|column| column.windows(2).all(|pair| pair[0].rank >= pair[1].rank))
+    }
+
+    // check that the board is one that could come up in a real game: every card in the deck
+    // is somewhere exactly once per deck, and everything is where it's allowed to be
+    pub fn validate(&self) -> std::result::Result<(), BoardError> {
+        let state = &self.state;
+        check_rules(&state.rules)?;
+        if state.columns.len() != state.rules.columns
+            || state.free_cells.len() != state.rules.free_cells
+        {
+            return Err(BoardError::WrongShape);
+        }
+        let foundations_in_order = state.foundations.len() == state.rules.foundations()
+            && state
+                .foundations
+                .iter()
+                .enumerate()
+                .all(|(n, card)| usize::from(card.suit) == n % 4);
+        if !foundations_in_order {
+            return Err(BoardError::BadFoundations);
+        }
+        if let Some(card) = state.foundations.iter().find(|c| c.rank > 13) {
+            return Err(BoardError::BadRank {
+                rank: card.rank,
+                suit: card.suit,
+            });
+        }
+
+        let cards = state
+            .columns
+            .iter()
+            .flat_map(|column| column.iter())
+            .chain(state.free_cells.iter().flatten())
+            .chain(state.floating.iter())
+            .chain(state.floating_stack.iter().flatten());
+        let mut counts = [[0; 14]; 4];
+        for card in cards {
+            if card.rank < 1 || card.rank > 13 {
+                return Err(BoardError::BadRank {
+                    rank: card.rank,
+                    suit: card.suit,
+                });
+            }
+            counts[usize::from(card.suit)][card.rank as usize] += 1;
+        }
+        // a foundation holds every card of its suit up to the one on top
+        for foundation in &state.foundations {
+            for rank in 1..=foundation.rank {
+                counts[usize::from(foundation.suit)][rank as usize] += 1;
+            }
+        }
+        for (suit, counts) in counts.iter().enumerate() {
+            for (rank, &count) in counts.iter().enumerate().skip(1) {
+                let card = Card::new(rank as u8, suit.try_into().unwrap());
+                // there's one of every card for each deck
+                if count < state.rules.decks {
+                    return Err(BoardError::MissingCard { card });
+                } else if count > state.rules.decks {
+                    return Err(BoardError::DuplicateCard { card, count });
+                }
+            }
+        }
+
+        if state.floating.is_some() && state.floating_stack.is_some() {
+            return Err(BoardError::HoldingTwice);
+        }
+        if let Some(n) = state.stack_source {
+            if state.floating_stack.is_none() || n >= state.columns.len() {
+                return Err(BoardError::BadStackSource { column: n });
+            }
+        }
+        // the view is saved along with the state, so it could disagree
+        if Board::from(state.clone()).view != self.view {
+            return Err(BoardError::StaleView);
+        }
+        Ok(())
+    }
+
+    // find the max number of cards the player can move at once as a stack onto a column.
+    // each free cell holds one card along the way, and each empty column doubles what
+    // can be moved, since a whole stack can be parked there.
+    // an empty destination column can't be used to help, so it's left out of the count.
+    // without a destination, this is the max number of cards the player can pick up at once
+    // under rules without that limit, any number of cards can be moved
+    fn max_stack_size(&self, destination: Option<usize>) -> usize {
+        if !self.state.rules.limited_stacks {
+            return usize::MAX;
+        }
+        let (num_empty_free_cells, num_empty_columns) = self.stack_space(destination);
+        (1 + num_empty_free_cells) * 2usize.pow(num_empty_columns as u32)
+    }
+
+    // the empty free cells and empty columns that can help move a stack.
+    // when only kings can go into empty columns, they can't hold cards along the way
+    fn stack_space(&self, destination: Option<usize>) -> (usize, usize) {
         let num_empty_free_cells: usize = self
             .state
             .free_cells
             .iter()
             .map(|&c| if None == c { 1 } else { 0 })
             .sum();
-        1 + num_empty_free_cells
+        let num_empty_columns = self
+            .state
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|&(n, column)| {
+                column.is_empty() && Some(n) != destination && Some(n) != self.state.stack_source
+            })
+            .count();
+        match self.state.rules.empty_columns {
+            EmptyColumns::AnyCard => (num_empty_free_cells, num_empty_columns),
+            EmptyColumns::KingsOnly => (num_empty_free_cells, 0),
+        }
     }
 
-    // move one arbitrary card to a foundation, if possible. returns true if it moved a card
-    pub fn auto_move_to_foundations(&self) -> Option<Self> {
+    // why a stack can't be moved, for when it's bigger than max_stack_size
+    fn stack_too_large(&self, destination: Option<usize>) -> MoveRejection {
+        let (free_cells, empty_columns) = self.stack_space(destination);
+        MoveRejection::StackTooLarge {
+            limit: self.max_stack_size(destination),
+            free_cells,
+            empty_columns,
+        }
+    }
+
+    // check whether a card can go on the end of a column
+    fn column_accepts(
+        &self,
+        card: Card,
+        column: &[Card],
+    ) -> std::result::Result<(), MoveRejection> {
+        match column.last() {
+            Some(&base) if !card.stacks_on(&base, &self.state.rules) => {
+                Err(MoveRejection::DoesNotFit { card, onto: base })
+            }
+            None if self.state.rules.empty_columns == EmptyColumns::KingsOnly
+                && card.rank != 13 =>
+            {
+                Err(MoveRejection::KingsOnly { card })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // why there's no single held card to place
+    fn nothing_single_held(&self) -> MoveRejection {
+        if self.state.floating_stack.is_some() {
+            MoveRejection::SingleCardsOnly
+        } else {
+            MoveRejection::NoCardsHeld
+        }
+    }
+
+    // move one arbitrary card from a free cell or column to a foundation, if the policy allows
+    pub fn auto_move_to_foundations(&self, policy: AutoMovePolicy) -> Option<Self> {
         if self.state.floating != None || self.state.floating_stack != None {
             return None;
         }
-        for (index, column_card) in self
+        let allowed = |card: Card| match policy {
+            AutoMovePolicy::Off => false,
+            AutoMovePolicy::Safe => self.can_auto_move(card),
+            AutoMovePolicy::Aggressive => self.fits_on_foundation(card),
+        };
+        // cards in free cells go first, since moving them frees up the cell
+        let free_cell_cards = self
+            .state
+            .free_cells
+            .iter()
+            .enumerate()
+            .map(|(index, &c)| (CardAddress::FreeCell(index), c));
+        let column_cards = self
             .state
             .columns
             .iter()
-            .map(|c| match c.last() {
-                Some(&v) => Some(v),
-                None => None,
-            })
             .enumerate()
-            .collect::<Vec<(usize, Option<Card>)>>()
-        {
-            let mut result = self.clone();
-            if let Some(card) = column_card {
-                if result.can_auto_move(card) {
-                    result = result.pick_up_card(CardAddress::Column(index)).unwrap();
-                    result = result.place(CardAddress::Foundation(card.suit)).unwrap();
-                    return Some(result);
+            .map(|(index, c)| (CardAddress::Column(index), c.last().copied()));
+        for (address, maybe_card) in free_cell_cards.chain(column_cards) {
+            if let Some(card) = maybe_card {
+                if allowed(card) {
+                    let result = self.pick_up_card(address).unwrap();
+                    return Some(result.place(CardAddress::Foundation(card.suit)).unwrap());
                 }
             }
         }
@@ -348,34 +794,49 @@ impl Board {
     }
 
     // true if a card can be auto-moved, i.e. it can move to a foundation and nothing else can stack on it
-    fn can_auto_move(&self, card: Card) -> bool {
-        if self.state.foundations[usize::from(card.suit)].rank != card.rank - 1 {
+    pub fn can_auto_move(&self, card: Card) -> bool {
+        if !self.fits_on_foundation(card) {
             return false;
         }
-        match card.suit.colour() {
-            Colour::Red => {
-                // "clubs" is false if there is any club not yet in the foundations which can stack on this card
-                let clubs = self.state.foundations[usize::from(Suit::Clubs)].rank >= card.rank - 1
-                    || self.can_auto_move(Card::new(card.rank - 1, Suit::Clubs));
-                // "spades" is false if there is any spade not yet in the foundations which can stack on this card
-                let spades = self.state.foundations[usize::from(Suit::Spades)].rank
-                    >= card.rank - 1
-                    || self.can_auto_move(Card::new(card.rank - 1, Suit::Spades));
-                clubs && spades
-            }
-            Colour::Black => {
-                // "diamonds" is false if there is any diamond not yet in the foundations which can stack on this card
-                let diamonds = self.state.foundations[usize::from(Suit::Diamonds)].rank
-                    >= card.rank - 1
-                    || self.can_auto_move(Card::new(card.rank - 1, Suit::Diamonds));
-                // "hearts" is false if there is any heart not yet in the foundations which can stack on this card
-                let hearts = self.state.foundations[usize::from(Suit::Hearts)].rank
-                    >= card.rank - 1
-                    || self.can_auto_move(Card::new(card.rank - 1, Suit::Hearts));
-                hearts && diamonds
+        // "done" is false if there is any card of the suit not yet in the foundations which can stack
+        // on this card. with one deck, a card that's about to be auto-moved itself doesn't count
+        let done = |suit: Suit| {
+            self.lowest_foundation(suit) >= card.rank - 1
+                || (self.state.rules.decks == 1
+                    && self.can_auto_move(Card::new(card.rank - 1, suit)))
+        };
+        match (self.state.rules.building, card.suit.colour()) {
+            (Building::SameSuit, _) => self.lowest_foundation(card.suit) >= card.rank - 1,
+            (Building::AlternateColours, Colour::Red) => done(Suit::Clubs) && done(Suit::Spades),
+            (Building::AlternateColours, Colour::Black) => {
+                done(Suit::Diamonds) && done(Suit::Hearts)
             }
         }
     }
+
+    fn fits_on_foundation(&self, card: Card) -> bool {
+        self.state
+            .foundations
+            .iter()
+            .any(|f| card.fits_on_foundation(f))
+    }
+
+    // the rank that every foundation of a suit has reached
+    fn lowest_foundation(&self, suit: Suit) -> u8 {
+        self.state
+            .foundations
+            .iter()
+            .filter(|f| f.suit == suit)
+            .map(|f| f.rank)
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+fn check_rules(rules: &Rules) -> std::result::Result<(), BoardError> {
+    rules
+        .check()
+        .map_err(|reason| BoardError::BadRules { reason })
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -387,12 +848,9 @@ pub struct BoardView {
 }
 
 impl BoardView {
+    // won when every foundation has reached its king, however many foundations there are
     pub fn is_won(&self) -> bool {
-        self.foundations
-            .iter()
-            .map(|c| c.rank)
-            .fold((true, 13), |(eq, val), next| (next == val && eq, val))
-            .0
+        self.foundations.iter().all(|c| c.rank == 13)
     }
 }
 
@@ -445,6 +903,7 @@ pub mod inspect {
     #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
     pub struct StateContainer(State);
 
+    #[cfg(test)]
     pub fn board_get_state(game: &Board) -> StateContainer {
         StateContainer(game.state.clone())
     }
@@ -455,8 +914,8 @@ pub mod inspect {
 
     #[cfg(test)]
     pub fn board_from_columns(columns: Vec<Vec<Card>>) -> Board {
-        let mut game = Board::empty().state;
-        game.columns = columns;
+        let mut game = Board::empty(&Rules::default()).state;
+        game.columns = columns.into_iter().map(Arc::new).collect();
         game.into()
     }
 }
@@ -0,0 +1,123 @@
+// reads boards back from the layout that BoardView's Display writes, so that positions
+// can be written by hand.
+// the first line holds the free cells and then the foundations, and after a blank line
+// each line is one row of the columns. every slot is a card followed by a space, or four
+// spaces when it's empty. an empty slot can also be written as "." to keep hand-written
+// positions lined up. cards can be written as they're displayed ("10♥") or in ascii ("TH")
+
+use std::str::FromStr;
+
+use super::*;
+
+// positions are read into the standard layout, unless they're read for other rules
+impl FromStr for Board {
+    type Err = ParseError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Board::from_layout(s, &Rules::default())
+    }
+}
+
+impl Board {
+    // read a position laid out for a set of rules, which say how many free cells,
+    // foundations and columns there are
+    pub fn from_layout(s: &str, rules: &Rules) -> std::result::Result<Self, ParseError> {
+        let bad = |line: usize, reason: LayoutError| ParseError::BadLayout { line, reason };
+        check_rules(rules)?;
+        let mut state = Board::empty(rules).state;
+        let mut lines = s.lines().enumerate().map(|(n, line)| (n + 1, line));
+
+        let (line, top) = lines.next().ok_or_else(|| bad(1, LayoutError::Empty))?;
+        let top = split_slots(top);
+        if top.len() > rules.free_cells + state.foundations.len() {
+            return Err(bad(line, LayoutError::TooManyCells));
+        }
+        for (i, slot) in top.into_iter().enumerate() {
+            let card = slot.map(str::parse::<Card>).transpose()?;
+            if i < rules.free_cells {
+                state.free_cells[i] = card;
+            } else if let Some(card) = card {
+                // foundations are always in suit order, even when they're empty
+                let foundation = &mut state.foundations[i - rules.free_cells];
+                if card.suit != foundation.suit {
+                    return Err(bad(line, LayoutError::FoundationSuit));
+                }
+                *foundation = card;
+            }
+        }
+
+        state.columns = vec![CardColumn::default(); rules.columns];
+        let mut row = 0;
+        for (line, text) in lines {
+            if text.trim().is_empty() {
+                continue;
+            }
+            if state.floating.is_some() || state.floating_stack.is_some() {
+                return Err(bad(line, LayoutError::BadFloating));
+            }
+            // the cards being held, as in "-> 5♥,4♠,"
+            if let Some(held) = text.trim().strip_prefix("->") {
+                let mut cards = held
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(str::parse)
+                    .collect::<std::result::Result<Vec<Card>, _>>()?;
+                if cards.len() == 1 {
+                    state.floating = cards.pop();
+                } else if !cards.is_empty() {
+                    state.floating_stack = Some(cards);
+                }
+                continue;
+            }
+            let slots = split_slots(text);
+            if slots.len() > rules.columns {
+                return Err(bad(line, LayoutError::TooManyColumns));
+            }
+            for (column, slot) in state.columns.iter_mut().zip(slots) {
+                if let Some(card) = slot {
+                    // a card can only go on the end of a column with a card in every row above
+                    if column.len() != row {
+                        return Err(bad(line, LayoutError::Gap));
+                    }
+                    Arc::make_mut(column).push(card.parse()?);
+                }
+            }
+            row += 1;
+        }
+
+        let board: Board = state.into();
+        board.validate()?;
+        Ok(board)
+    }
+}
+
+impl FromStr for BoardView {
+    type Err = ParseError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(s.parse::<Board>()?.view)
+    }
+}
+
+// splits a line into its slots, with None for the empty ones
+fn split_slots(line: &str) -> Vec<Option<&str>> {
+    let mut slots = Vec::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("    ") {
+            slots.push(None);
+            rest = after;
+            continue;
+        }
+        // fewer than four spaces is only padding, either before a card or at the end of the line
+        let trimmed = rest.trim_start();
+        if trimmed.is_empty() {
+            break;
+        }
+        let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+        let (word, after) = trimmed.split_at(end);
+        slots.push(if word == "." { None } else { Some(word) });
+        // the space after a card belongs to the card's slot
+        rest = after.strip_prefix(' ').unwrap_or(after);
+    }
+    slots
+}
@@ -39,14 +39,14 @@ fn test_moves() {
         spread.pick_up_stack(CardAddress::Column(1), 4),
         Err(MoveError::CannotPickUp {
             from: CardAddress::Column(1),
-            reason: REASON_STACK_LARGER_THAN_COLUMN.to_string(),
+            reason: MoveRejection::StackLargerThanColumn { column_size: 3 },
         }),
     );
     assert_eq!(
         spread.pick_up_stack(CardAddress::Column(3), 3),
         Err(MoveError::CannotPickUp {
             from: CardAddress::Column(3),
-            reason: REASON_UNSOUND_STACK.to_string(),
+            reason: MoveRejection::UnsoundStack { position: 2 },
         }),
     );
     spread = spread.pick_up_stack(CardAddress::Column(0), 4).unwrap();
@@ -54,14 +54,14 @@ fn test_moves() {
         spread.pick_up_stack(CardAddress::Column(0), 2),
         Err(MoveError::CannotPickUp {
             from: CardAddress::Column(0),
-            reason: REASON_ALREADY_HOLDING.to_string(),
+            reason: MoveRejection::AlreadyHolding,
         })
     );
     assert_eq!(
         spread.pick_up_card(CardAddress::Column(0)),
         Err(MoveError::CannotPickUp {
             from: CardAddress::Column(0),
-            reason: REASON_ALREADY_HOLDING.to_string(),
+            reason: MoveRejection::AlreadyHolding,
         })
     );
     assert_eq!(
@@ -72,7 +72,7 @@ fn test_moves() {
         spread.place(CardAddress::FreeCell(0)),
         Err(MoveError::CannotPlace {
             to: CardAddress::FreeCell(0),
-            reason: REASON_DOES_NOT_FIT.to_string(),
+            reason: MoveRejection::SingleCardsOnly,
         })
     );
     assert_eq!(
@@ -86,13 +86,21 @@ fn test_moves() {
         spread.view().columns[1].last().unwrap(),
         &Card::new(1, Suit::Spades)
     );
+    // four free cells and an empty column are enough to pick up ten cards,
+    // but not to move six of them into the empty column
+    spread = spread.pick_up_stack(CardAddress::Column(1), 6).unwrap();
     assert_eq!(
-        spread.pick_up_stack(CardAddress::Column(1), 6),
-        Err(MoveError::CannotPickUp {
-            from: CardAddress::Column(1),
-            reason: REASON_STACK_TOO_LARGE.to_string(),
+        spread.place(CardAddress::Column(2)),
+        Err(MoveError::CannotPlace {
+            to: CardAddress::Column(2),
+            reason: MoveRejection::StackTooLarge {
+                limit: 5,
+                free_cells: 4,
+                empty_columns: 0,
+            },
         })
     );
+    spread = spread.place(CardAddress::Column(1)).unwrap();
     spread = spread.pick_up_stack(CardAddress::Column(1), 5).unwrap();
     spread = spread.place(CardAddress::Column(1)).unwrap();
 
@@ -102,14 +110,20 @@ fn test_moves() {
         spread.place(CardAddress::Column(0)),
         Err(MoveError::CannotPlace {
             to: CardAddress::Column(0),
-            reason: REASON_DOES_NOT_FIT.to_string(),
+            reason: MoveRejection::DoesNotFit {
+                card: Card::new(1, Suit::Spades),
+                onto: Card::new(5, Suit::Spades),
+            },
         })
     );
     assert_eq!(
         spread.place(CardAddress::Foundation(Suit::Hearts)),
         Err(MoveError::CannotPlace {
             to: CardAddress::Foundation(Suit::Hearts),
-            reason: REASON_DOES_NOT_FIT.to_string(),
+            reason: MoveRejection::DoesNotFit {
+                card: Card::new(1, Suit::Spades),
+                onto: Card::new(0, Suit::Hearts),
+            },
         })
     );
     spread = spread.place(CardAddress::Foundation(Suit::Spades)).unwrap();
@@ -126,7 +140,7 @@ fn test_moves() {
         spread.place(CardAddress::FreeCell(0)),
         Err(MoveError::CannotPlace {
             to: CardAddress::FreeCell(0),
-            reason: REASON_DOES_NOT_FIT.to_string(),
+            reason: MoveRejection::CellOccupied,
         })
     );
     spread = spread.place(CardAddress::FreeCell(1)).unwrap();
@@ -142,7 +156,7 @@ fn test_moves() {
         spread.pick_up_card(CardAddress::FreeCell(2)),
         Err(MoveError::CannotPickUp {
             from: CardAddress::FreeCell(2),
-            reason: REASON_EMPTY_ADDRESS.to_string(),
+            reason: MoveRejection::EmptyAddress,
         })
     );
     spread = spread.pick_up_card(CardAddress::FreeCell(1)).unwrap();
@@ -154,6 +168,191 @@ fn test_moves() {
     let _ = spread.place(CardAddress::Column(2)).unwrap();
 }
 
+#[test]
+fn test_supermoves() {
+    let mut spread = inspect::board_from_columns(vec![
+        vec![
+            Card::new(12, Suit::Hearts),
+            Card::new(11, Suit::Spades),
+            Card::new(10, Suit::Hearts),
+            Card::new(9, Suit::Spades),
+            Card::new(8, Suit::Hearts),
+            Card::new(7, Suit::Spades),
+            Card::new(6, Suit::Hearts),
+            Card::new(5, Suit::Spades),
+            Card::new(4, Suit::Hearts),
+        ],
+        vec![Card::new(12, Suit::Diamonds)],
+        vec![Card::new(3, Suit::Clubs), Card::new(2, Suit::Clubs)],
+        Vec::new(),
+    ]);
+    spread = spread.pick_up_card(CardAddress::Column(2)).unwrap();
+    spread = spread.place(CardAddress::FreeCell(0)).unwrap();
+
+    // three free cells and one empty column let you move eight cards
+    assert_eq!(
+        spread.pick_up_stack(CardAddress::Column(0), 9),
+        Err(MoveError::CannotPickUp {
+            from: CardAddress::Column(0),
+            reason: MoveRejection::StackTooLarge {
+                limit: 8,
+                free_cells: 3,
+                empty_columns: 1,
+            },
+        })
+    );
+    spread = spread.pick_up_stack(CardAddress::Column(0), 8).unwrap();
+
+    // but only four if the empty column is where they're going
+    assert_eq!(
+        spread.place(CardAddress::Column(3)),
+        Err(MoveError::CannotPlace {
+            to: CardAddress::Column(3),
+            reason: MoveRejection::StackTooLarge {
+                limit: 4,
+                free_cells: 3,
+                empty_columns: 0,
+            },
+        })
+    );
+    spread = spread.place(CardAddress::Column(1)).unwrap();
+    assert_eq!(spread.view().columns[1].len(), 9);
+    spread = spread.pick_up_stack(CardAddress::Column(1), 4).unwrap();
+    spread = spread.place(CardAddress::Column(3)).unwrap();
+    assert_eq!(spread.view().columns[3].len(), 4);
+}
+
+#[test]
+fn test_apply() {
+    let game = inspect::board_from_columns(vec![
+        vec![
+            Card::new(3, Suit::Clubs),
+            Card::new(2, Suit::Hearts),
+            Card::new(1, Suit::Spades),
+        ],
+        vec![Card::new(4, Suit::Diamonds)],
+    ]);
+    let stack = Move {
+        from: CardAddress::Column(0),
+        to: CardAddress::Column(1),
+        count: 3,
+    };
+    let to_free_cell = Move {
+        from: CardAddress::Column(0),
+        to: CardAddress::FreeCell(2),
+        count: 1,
+    };
+    let too_many = Move {
+        from: CardAddress::Column(0),
+        to: CardAddress::FreeCell(2),
+        count: 2,
+    };
+    assert!(game.is_legal(&stack));
+    assert!(game.is_legal(&to_free_cell));
+    assert!(!game.is_legal(&too_many));
+
+    let moved = game.apply(&stack).unwrap();
+    assert!(!moved.has_floating());
+    assert!(moved.view().columns[0].is_empty());
+    assert_eq!(moved.view().columns[1].len(), 4);
+    assert_eq!(
+        moved.apply(&stack),
+        Err(MoveError::CannotPickUp {
+            from: CardAddress::Column(0),
+            reason: MoveRejection::StackLargerThanColumn { column_size: 0 },
+        })
+    );
+    assert_eq!(
+        game.apply(&too_many),
+        Err(MoveError::CannotPlace {
+            to: CardAddress::FreeCell(2),
+            reason: MoveRejection::SingleCardsOnly,
+        })
+    );
+
+    // the move goes through the same steps as picking up & placing the cards
+    let by_hand = game
+        .pick_up_card(CardAddress::Column(0))
+        .unwrap()
+        .place(CardAddress::FreeCell(2))
+        .unwrap();
+    assert_eq!(game.apply(&to_free_cell).unwrap(), by_hand);
+}
+
+#[test]
+fn test_legal_moves() {
+    let game = inspect::board_from_columns(vec![
+        vec![Card::new(2, Suit::Clubs), Card::new(1, Suit::Hearts)],
+        vec![Card::new(3, Suit::Hearts), Card::new(2, Suit::Spades)],
+        Vec::new(),
+    ])
+    .pick_up_card(CardAddress::Column(0))
+    .unwrap()
+    .place(CardAddress::FreeCell(1))
+    .unwrap();
+    let moves = game.legal_moves();
+    let expected = vec![
+        // the ace in the free cell
+        (
+            CardAddress::FreeCell(1),
+            CardAddress::Foundation(Suit::Hearts),
+            1,
+        ),
+        (CardAddress::FreeCell(1), CardAddress::Column(0), 1),
+        (CardAddress::FreeCell(1), CardAddress::Column(1), 1),
+        (CardAddress::FreeCell(1), CardAddress::Column(2), 1),
+        // the two of clubs
+        (CardAddress::Column(0), CardAddress::FreeCell(0), 1),
+        (CardAddress::Column(0), CardAddress::FreeCell(2), 1),
+        (CardAddress::Column(0), CardAddress::FreeCell(3), 1),
+        (CardAddress::Column(0), CardAddress::Column(2), 1),
+        // the two of spades, and the stack it's in
+        (CardAddress::Column(1), CardAddress::FreeCell(0), 1),
+        (CardAddress::Column(1), CardAddress::FreeCell(2), 1),
+        (CardAddress::Column(1), CardAddress::FreeCell(3), 1),
+        (CardAddress::Column(1), CardAddress::Column(2), 1),
+        (CardAddress::Column(1), CardAddress::Column(2), 2),
+    ];
+    let expected: Vec<Move> = expected
+        .into_iter()
+        .map(|(from, to, count)| Move { from, to, count })
+        .collect();
+    assert_eq!(moves, expected);
+    assert!(moves.iter().all(|mv| game.is_legal(mv)));
+}
+
+#[test]
+fn test_stuck() {
+    // black cards never stack on each other, and there are no aces to move up
+    let mut game = inspect::board_from_columns(vec![
+        vec![
+            Card::new(13, Suit::Clubs),
+            Card::new(13, Suit::Spades),
+            Card::new(12, Suit::Clubs),
+            Card::new(12, Suit::Spades),
+            Card::new(11, Suit::Clubs),
+            Card::new(11, Suit::Spades),
+            Card::new(10, Suit::Clubs),
+            Card::new(10, Suit::Spades),
+        ],
+        vec![Card::new(9, Suit::Clubs)],
+        Vec::new(),
+    ]);
+    for n in 0..4 {
+        assert!(!game.is_stuck());
+        game = game.pick_up_card(CardAddress::Column(0)).unwrap();
+        // holding cards is never stuck
+        assert!(!game.is_stuck());
+        game = game.place(CardAddress::FreeCell(n)).unwrap();
+    }
+    // the queen of spades can still go into the empty column
+    assert!(!game.is_stuck());
+    game = game.pick_up_card(CardAddress::Column(0)).unwrap();
+    game = game.place(CardAddress::Column(2)).unwrap();
+    assert!(game.legal_moves().is_empty());
+    assert!(game.is_stuck());
+}
+
 #[test]
 fn auto_move() {
     let mut game = inspect::board_from_columns(vec![
@@ -175,7 +374,7 @@ fn auto_move() {
             Card::new(1, Suit::Hearts),
         ],
     ]);
-    while let Some(new_state) = game.auto_move_to_foundations() {
+    while let Some(new_state) = game.auto_move_to_foundations(AutoMovePolicy::Safe) {
         game = new_state;
     }
     assert_eq!(
@@ -189,18 +388,225 @@ fn auto_move() {
     )
 }
 
+#[test]
+fn auto_move_from_free_cells() {
+    let mut game = inspect::board_from_columns(vec![
+        vec![Card::new(2, Suit::Hearts), Card::new(1, Suit::Hearts)],
+        vec![Card::new(2, Suit::Spades), Card::new(1, Suit::Spades)],
+    ]);
+    // park both aces and the two of spades in free cells, under their own cards
+    game = game.pick_up_card(CardAddress::Column(1)).unwrap();
+    game = game.place(CardAddress::FreeCell(0)).unwrap();
+    game = game.pick_up_card(CardAddress::Column(1)).unwrap();
+    game = game.place(CardAddress::FreeCell(1)).unwrap();
+    game = game.pick_up_card(CardAddress::Column(0)).unwrap();
+    game = game.place(CardAddress::FreeCell(2)).unwrap();
+    while let Some(new_state) = game.auto_move_to_foundations(AutoMovePolicy::Safe) {
+        game = new_state;
+    }
+    assert_eq!(game.view().free_cells, vec![None; 4]);
+    assert_eq!(
+        game.view().foundations,
+        vec![
+            Card::new(0, Suit::Clubs),
+            Card::new(0, Suit::Diamonds),
+            Card::new(2, Suit::Hearts),
+            Card::new(2, Suit::Spades),
+        ]
+    )
+}
+
+#[test]
+fn auto_move_policies() {
+    // the three of hearts is playable, but the two of spades under it could still go on it
+    let mut game = inspect::board_from_columns(vec![
+        vec![Card::new(2, Suit::Hearts), Card::new(1, Suit::Hearts)],
+        vec![Card::new(2, Suit::Spades), Card::new(3, Suit::Hearts)],
+    ]);
+    assert_eq!(game.auto_move_to_foundations(AutoMovePolicy::Off), None);
+    while let Some(new_state) = game.auto_move_to_foundations(AutoMovePolicy::Safe) {
+        game = new_state;
+    }
+    assert_eq!(game.view().foundations[2], Card::new(2, Suit::Hearts));
+    let game = game
+        .auto_move_to_foundations(AutoMovePolicy::Aggressive)
+        .unwrap();
+    assert_eq!(game.view().columns[1], vec![Card::new(2, Suit::Spades)]);
+    assert_eq!(game.view().foundations[2], Card::new(3, Suit::Hearts));
+
+    assert_eq!("Aggressive".parse(), Ok(AutoMovePolicy::Aggressive));
+    assert!("sometimes".parse::<AutoMovePolicy>().is_err());
+    assert_eq!(AutoMovePolicy::Aggressive.next(), AutoMovePolicy::Off);
+}
+
+#[test]
+fn auto_complete() {
+    // each suit in its own column, with the ace on top
+    let mut columns: Vec<Vec<Card>> = [Suit::Clubs, Suit::Diamonds, Suit::Spades, Suit::Hearts]
+        .iter()
+        .map(|&suit| (1..=13).rev().map(|n| Card::new(n, suit)).collect())
+        .collect();
+    let mut game = inspect::board_from_columns(columns.clone());
+    assert!(game.can_auto_complete());
+    while let Some(new_state) = game.auto_move_to_foundations(AutoMovePolicy::Aggressive) {
+        game = new_state;
+    }
+    assert!(game.view().is_won());
+    assert!(game.can_auto_complete());
+
+    // a two on top of an ace has to be moved out of the way first
+    columns[0].swap(11, 12);
+    let game = inspect::board_from_columns(columns);
+    assert!(!game.can_auto_complete());
+    let game = game.pick_up_card(CardAddress::Column(1)).unwrap();
+    assert!(!game.can_auto_complete());
+}
+
+#[test]
+fn shares_columns() {
+    let game = Board::new_game(0, &Rules::default()).unwrap();
+    let moved = game
+        .apply(&Move {
+            from: CardAddress::Column(0),
+            to: CardAddress::FreeCell(0),
+            count: 1,
+        })
+        .unwrap();
+    // only the column that changed is copied
+    assert!(!Arc::ptr_eq(
+        &game.state.columns[0],
+        &moved.state.columns[0]
+    ));
+    for n in 1..8 {
+        assert!(Arc::ptr_eq(&game.state.columns[n], &moved.state.columns[n]));
+    }
+}
+
+#[test]
+fn test_hash() {
+    let game = Board::new_game(0, &Rules::default()).unwrap();
+    // the same on every run & platform
+    assert_eq!(game.position_hash(), 5457339740906288874);
+    assert_eq!(game.canonical_hash(), 741593232015707524);
+
+    // moving cards between free cells or swapping columns only changes the exact hash
+    let mut state = game.state.clone();
+    state.free_cells[0] = Arc::make_mut(&mut state.columns[0]).pop();
+    state.free_cells[1] = Arc::make_mut(&mut state.columns[1]).pop();
+    let in_cells = Board::from(state.clone());
+    state.free_cells.swap(0, 1);
+    state.columns.swap(2, 5);
+    let shuffled = Board::from(state);
+    assert_ne!(in_cells.position_hash(), game.position_hash());
+    assert_ne!(in_cells.canonical_hash(), game.canonical_hash());
+    assert_ne!(shuffled.position_hash(), in_cells.position_hash());
+    assert_eq!(shuffled.canonical_hash(), in_cells.canonical_hash());
+
+    // two of the same card don't cancel each other out
+    let mut state = Board::empty(&Rules::double_deck()).state;
+    let empty = Board::from(state.clone());
+    state.free_cells[0] = Some(Card::new(1, Suit::Hearts));
+    state.free_cells[1] = Some(Card::new(1, Suit::Hearts));
+    assert_ne!(Board::from(state).canonical_hash(), empty.canonical_hash());
+}
+
+#[test]
+fn test_code() {
+    for name in VARIANT_NAMES {
+        let rules: Rules = name.parse().unwrap();
+        let game = Board::new_game(7, &rules).unwrap();
+        assert_eq!(Board::from_bytes(&game.to_bytes()), Ok(game.clone()));
+        assert_eq!(Board::from_code(&game.to_code()), Ok(game));
+    }
+
+    // a fresh deal fits in a few dozen bytes, and the text is one character per six bits
+    let game = Board::new_game(7, &Rules::default()).unwrap();
+    assert_eq!(game.to_bytes().len(), 57);
+    assert_eq!(game.to_code().len(), 76);
+
+    // cards on the foundations, and a held stack
+    let mut state = inspect::board_from_columns(
+        [Suit::Clubs, Suit::Diamonds, Suit::Spades, Suit::Hearts]
+            .iter()
+            .map(|&suit| (1..=13).rev().map(|n| Card::new(n, suit)).collect())
+            .collect(),
+    )
+    .state;
+    state.rules = Rules {
+        columns: 4,
+        ..Rules::bakers_game()
+    };
+    let mut game = Board::from(state);
+    game = game.pick_up_card(CardAddress::Column(0)).unwrap();
+    game = game.place(CardAddress::Foundation(Suit::Clubs)).unwrap();
+    game = game.pick_up_stack(CardAddress::Column(0), 3).unwrap();
+    assert_eq!(Board::from_code(&game.to_code()), Ok(game.clone()));
+
+    let code = game.to_code();
+    let bad = |reason| Err(ParseError::BadCode { reason });
+    assert_eq!(
+        Board::from_code(&code[..code.len() - 4]),
+        bad(CodeError::TooShort)
+    );
+    assert_eq!(Board::from_code("not a code"), bad(CodeError::Character));
+    assert_eq!(
+        Board::from_code(&format!("C{}", &code[1..])),
+        bad(CodeError::Version)
+    );
+    // the fourth symbol is how many cards are dealt to the free cells, and 60 is '8'
+    assert_eq!(
+        Board::from_code(&format!("{}8{}", &code[..3], &code[4..])),
+        bad(CodeError::Rules {
+            reason: RulesError::TooManyDealt
+        })
+    );
+}
+
 #[test]
 fn test_rng() {
     for seed in 0..10 {
-        let a = Board::new_game(seed);
-        let b = Board::new_game(seed);
+        let a = Board::new_game(seed, &Rules::default()).unwrap();
+        let b = Board::new_game(seed, &Rules::default()).unwrap();
         assert_eq!(a, b);
     }
 }
 
+#[test]
+fn test_microsoft_deal() {
+    // deal #1 as it appears in Microsoft FreeCell, one row at a time
+    let expected = [
+        "J♦ 2♦ 9♥ J♣ 5♦ 7♥ 7♣ 5♥",
+        "K♦ K♣ 9♠ 5♠ 1♦ Q♣ K♥ 3♥",
+        "2♠ K♠ 9♦ Q♦ J♠ 1♠ 1♥ 3♣",
+        "4♣ 5♣ 10♠ Q♥ 4♥ 1♣ 4♦ 7♠",
+        "3♠ 10♦ 4♠ 10♥ 8♥ 2♣ J♥ 7♦",
+        "6♦ 8♠ 8♦ Q♠ 6♣ 3♦ 8♣ 10♣",
+        "6♠ 9♣ 2♥ 6♥",
+    ];
+    let game = Board::new_microsoft_game(1, &Rules::default()).unwrap();
+    for (row, expected_row) in expected.iter().enumerate() {
+        let cards: Vec<String> = game
+            .view()
+            .columns
+            .iter()
+            .filter_map(|column| column.get(row))
+            .map(|card| card.to_string())
+            .collect();
+        assert_eq!(&cards.join(" "), expected_row);
+    }
+    assert_eq!(
+        Board::new_microsoft_game(11982, &Rules::default()).unwrap(),
+        Board::new_microsoft_game(11982, &Rules::default()).unwrap()
+    );
+    assert_ne!(
+        Board::new_microsoft_game(1, &Rules::default()).unwrap(),
+        Board::new_microsoft_game(2, &Rules::default()).unwrap()
+    );
+}
+
 #[test]
 fn test_won() {
-    let mut game = Board::empty();
+    let mut game = Board::empty(&Rules::default());
     assert!(!game.view().is_won());
     game.state.foundations = vec![
         Card::new(13, Suit::Clubs),
@@ -214,3 +620,361 @@ fn test_won() {
     game = game.state.into(); // update view
     assert!(game.view().is_won());
 }
+
+#[test]
+fn test_parse() {
+    for seed in 0..5 {
+        let mut game = Board::new_game(seed, &Rules::default()).unwrap();
+        assert_eq!(game.view().to_string().parse(), Ok(game.clone()));
+        // play some moves so that free cells, foundations & empty columns get involved
+        for _ in 0..20 {
+            if let Some(mv) = game.legal_moves().first() {
+                game = game.apply(mv).unwrap();
+            }
+            let text = game.view().to_string();
+            assert_eq!(
+                text.parse::<BoardView>().as_ref(),
+                Ok(game.view()),
+                "{}",
+                text
+            );
+        }
+        // the held cards are read too
+        if let Ok(holding) = game.pick_up_card(CardAddress::Column(0)) {
+            let text = holding.view().to_string();
+            assert_eq!(text.parse::<BoardView>().as_ref(), Ok(holding.view()));
+        }
+    }
+    assert_eq!(
+        Board::new_microsoft_game(1, &Rules::default())
+            .unwrap()
+            .view()
+            .to_string()
+            .parse(),
+        Ok(Board::new_microsoft_game(1, &Rules::default()).unwrap())
+    );
+
+    // every variant reads back with its own rules
+    for name in VARIANT_NAMES {
+        let rules: Rules = name.parse().unwrap();
+        let mut game = Board::new_game(3, &rules).unwrap();
+        for _ in 0..10 {
+            let text = game.view().to_string();
+            assert_eq!(
+                Board::from_layout(&text, &rules),
+                Ok(game.clone()),
+                "{}",
+                text
+            );
+            if let Some(mv) = game.legal_moves().first() {
+                game = game.apply(mv).unwrap();
+            }
+        }
+    }
+
+    // positions can be written by hand in ascii
+    let game: Board = "KH  .   .   .   KC  KD  QH  JS\n\nQS  KS".parse().unwrap();
+    assert_eq!(game.view().free_cells[0], Some(Card::new(13, Suit::Hearts)));
+    assert_eq!(game.view().free_cells[1], None);
+    assert_eq!(game.view().foundations[3], Card::new(11, Suit::Spades));
+    assert_eq!(game.view().columns.len(), 8);
+    assert_eq!(game.view().columns[0], vec![Card::new(12, Suit::Spades)]);
+    assert_eq!(game.view().columns[1], vec![Card::new(13, Suit::Spades)]);
+    assert_eq!("10♥".parse(), Ok(Card::new(10, Suit::Hearts)));
+    assert_eq!("td".parse(), Ok(Card::new(10, Suit::Diamonds)));
+    assert_eq!("A♣".parse(), Ok(Card::new(1, Suit::Clubs)));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        "ZZ".parse::<Card>(),
+        Err(ParseError::BadCard {
+            text: "ZZ".to_string()
+        })
+    );
+    assert_eq!(
+        "11H".parse::<Card>(),
+        Err(ParseError::BadCard {
+            text: "11H".to_string()
+        })
+    );
+    assert_eq!(
+        "".parse::<Board>(),
+        Err(ParseError::BadLayout {
+            line: 1,
+            reason: LayoutError::Empty
+        })
+    );
+    assert_eq!(
+        "KH  .   .   .   KD  KC  QH  JS\n\nQS  KS".parse::<Board>(),
+        Err(ParseError::BadLayout {
+            line: 1,
+            reason: LayoutError::FoundationSuit
+        })
+    );
+    assert_eq!(
+        "KH  .   .   .   KC  KD  QH  JS\n\nQS\n    KS".parse::<Board>(),
+        Err(ParseError::BadLayout {
+            line: 4,
+            reason: LayoutError::Gap
+        })
+    );
+    assert_eq!(
+        "KH  .   .   .   KC  KD  QH  JS\n\nQS  KH".parse::<Board>(),
+        Err(ParseError::IllegalPosition(BoardError::DuplicateCard {
+            card: Card::new(13, Suit::Hearts),
+            count: 2
+        }))
+    );
+    assert_eq!(
+        "KH  .   .   .   KC  KD  QH  JS\n\nQS".parse::<Board>(),
+        Err(ParseError::IllegalPosition(BoardError::MissingCard {
+            card: Card::new(13, Suit::Spades)
+        }))
+    );
+}
+
+#[test]
+fn test_validate() {
+    let game = Board::new_game(0, &Rules::default()).unwrap();
+    assert_eq!(game.validate(), Ok(()));
+    let holding = game.pick_up_stack(CardAddress::Column(0), 1).unwrap();
+    assert_eq!(holding.validate(), Ok(()));
+
+    let mut state = game.state.clone();
+    Arc::make_mut(&mut state.columns[0]).pop();
+    assert!(matches!(
+        Board::from(state).validate(),
+        Err(BoardError::MissingCard { .. })
+    ));
+
+    let mut state = game.state.clone();
+    Arc::make_mut(&mut state.columns[0])[0] = state.columns[1][0];
+    assert!(matches!(
+        Board::from(state).validate(),
+        Err(BoardError::DuplicateCard { count: 2, .. })
+    ));
+
+    let mut state = game.state.clone();
+    Arc::make_mut(&mut state.columns[0])[0].rank = 14;
+    assert_eq!(
+        Board::from(state).validate(),
+        Err(BoardError::BadRank {
+            rank: 14,
+            suit: game.state.columns[0][0].suit
+        })
+    );
+
+    let mut state = game.state.clone();
+    state.foundations.swap(0, 1);
+    assert_eq!(
+        Board::from(state).validate(),
+        Err(BoardError::BadFoundations)
+    );
+
+    let mut state = holding.state.clone();
+    state.stack_source = Some(8);
+    assert_eq!(
+        Board::from(state).validate(),
+        Err(BoardError::BadStackSource { column: 8 })
+    );
+
+    let mut stale = game.clone();
+    stale.view.columns[0] = Vec::new();
+    assert_eq!(stale.validate(), Err(BoardError::StaleView));
+
+    let mut state = game.state.clone();
+    state.free_cells.pop();
+    assert_eq!(Board::from(state).validate(), Err(BoardError::WrongShape));
+
+    let mut state = game.state.clone();
+    state.rules.dealt_to_free_cells = 5;
+    assert_eq!(
+        Board::from(state).validate(),
+        Err(BoardError::BadRules {
+            reason: RulesError::TooManyDealt
+        })
+    );
+}
+
+#[test]
+fn test_variant_deals() {
+    for rules in &[
+        Rules::freecell(),
+        Rules::eight_off(),
+        Rules::seahaven_towers(),
+    ] {
+        for game in &[
+            Board::new_game(3, rules).unwrap(),
+            Board::new_microsoft_game(3, rules).unwrap(),
+        ] {
+            assert_eq!(game.validate(), Ok(()));
+            let view = game.view();
+            assert_eq!(view.columns.len(), rules.columns);
+            assert_eq!(view.free_cells.len(), rules.free_cells);
+            let dealt_to_free_cells = view.free_cells.iter().filter(|c| c.is_some()).count();
+            assert_eq!(dealt_to_free_cells, rules.dealt_to_free_cells);
+        }
+    }
+    let sizes: Vec<usize> = Board::new_game(3, &Rules::seahaven_towers())
+        .unwrap()
+        .view()
+        .columns
+        .iter()
+        .map(|c| c.len())
+        .collect();
+    assert_eq!(sizes, vec![5; 10]);
+    // custom free cells don't change the deal
+    let six_cells = Rules {
+        free_cells: 6,
+        ..Rules::freecell()
+    };
+    assert_eq!(
+        Board::new_game(3, &six_cells).unwrap().view().columns,
+        Board::new_game(3, &Rules::freecell())
+            .unwrap()
+            .view()
+            .columns
+    );
+
+    // rules that can't be dealt are refused, rather than dropping cards
+    let too_many_dealt = Rules {
+        free_cells: 2,
+        ..Rules::eight_off()
+    };
+    let refused = Err(BoardError::BadRules {
+        reason: RulesError::TooManyDealt,
+    });
+    assert_eq!(Board::new_game(3, &too_many_dealt), refused);
+    assert_eq!(Board::new_microsoft_game(3, &too_many_dealt), refused);
+}
+
+#[test]
+fn test_variant_rules() {
+    let columns = vec![
+        vec![Card::new(9, Suit::Hearts)],
+        vec![Card::new(8, Suit::Clubs), Card::new(8, Suit::Hearts)],
+        vec![Card::new(13, Suit::Spades), Card::new(12, Suit::Spades)],
+        Vec::new(),
+    ];
+    let mut state = inspect::board_from_columns(columns).state;
+
+    // building by alternate colours
+    let game: Board = state.clone().into();
+    let to_nine = Move {
+        from: CardAddress::Column(1),
+        to: CardAddress::Column(0),
+        count: 1,
+    };
+    assert!(!game.is_legal(&to_nine));
+
+    // building in suit
+    state.rules = Rules::bakers_game();
+    let game: Board = state.clone().into();
+    assert!(game.is_legal(&to_nine));
+    assert!(game.can_auto_move(Card::new(1, Suit::Hearts)));
+
+    // only kings in empty columns
+    state.rules = Rules::eight_off();
+    state.free_cells = vec![None; 8];
+    let game: Board = state.clone().into();
+    let to_empty = |from, count| Move {
+        from: CardAddress::Column(from),
+        to: CardAddress::Column(3),
+        count,
+    };
+    assert_eq!(
+        game.apply(&to_empty(0, 1)),
+        Err(MoveError::CannotPlace {
+            to: CardAddress::Column(3),
+            reason: MoveRejection::KingsOnly {
+                card: Card::new(9, Suit::Hearts)
+            },
+        })
+    );
+    assert!(game.is_legal(&to_empty(2, 2)));
+
+    // no limit on how many cards can move at once
+    let long_column: Vec<Card> = (1..=12)
+        .rev()
+        .map(|rank| Card::new(rank, [Suit::Hearts, Suit::Spades][rank as usize % 2]))
+        .collect();
+    let mut state =
+        inspect::board_from_columns(vec![long_column, vec![Card::new(13, Suit::Clubs)]]).state;
+    state.free_cells = vec![Some(Card::new(1, Suit::Clubs)); 4];
+    let moving_all = Move {
+        from: CardAddress::Column(0),
+        to: CardAddress::Column(1),
+        count: 12,
+    };
+    let game: Board = state.clone().into();
+    assert!(!game.is_legal(&moving_all));
+    state.rules = Rules::relaxed();
+    let game: Board = state.into();
+    assert!(game.is_legal(&moving_all));
+}
+
+#[test]
+fn test_double_deck() {
+    let rules = Rules::double_deck();
+    for game in &[
+        Board::new_game(5, &rules).unwrap(),
+        Board::new_microsoft_game(5, &rules).unwrap(),
+    ] {
+        assert_eq!(game.validate(), Ok(()));
+        let view = game.view();
+        assert_eq!(view.columns.len(), 10);
+        assert_eq!(view.free_cells.len(), 6);
+        assert_eq!(view.foundations.len(), 8);
+        let cards: usize = view.columns.iter().map(|c| c.len()).sum();
+        assert_eq!(cards, 104);
+    }
+
+    // both aces of a suit go up, onto separate foundations
+    let mut state = Board::empty(&rules).state;
+    state.columns = vec![
+        Arc::new(vec![Card::new(1, Suit::Hearts), Card::new(1, Suit::Hearts)]),
+        Arc::new(vec![Card::new(2, Suit::Hearts)]),
+    ];
+    let mut game: Board = state.into();
+    for _ in 0..2 {
+        assert!(game.can_auto_move(Card::new(1, Suit::Hearts)));
+        game = game.auto_move_to_foundations(AutoMovePolicy::Safe).unwrap();
+    }
+    let hearts: Vec<u8> = game
+        .view()
+        .foundations
+        .iter()
+        .filter(|f| f.suit == Suit::Hearts)
+        .map(|f| f.rank)
+        .collect();
+    assert_eq!(hearts, vec![1, 1]);
+    // the two of hearts could still have the other deck's black ace placed on it
+    assert!(!game.can_auto_move(Card::new(2, Suit::Hearts)));
+    game = game
+        .apply(&Move {
+            from: CardAddress::Column(1),
+            to: CardAddress::Foundation(Suit::Hearts),
+            count: 1,
+        })
+        .unwrap();
+    let hearts: Vec<u8> = game
+        .view()
+        .foundations
+        .iter()
+        .filter(|f| f.suit == Suit::Hearts)
+        .map(|f| f.rank)
+        .collect();
+    assert_eq!(hearts, vec![2, 1]);
+
+    let mut won = Board::empty(&rules).state;
+    won.foundations = won
+        .foundations
+        .iter()
+        .map(|f| Card::new(13, f.suit))
+        .collect();
+    assert!(Board::from(won.clone()).view().is_won());
+    won.foundations[7].rank = 12;
+    assert!(!Board::from(won).view().is_won());
+}
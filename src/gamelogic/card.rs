@@ -1,9 +1,11 @@
 use std::convert::TryFrom;
 use std::fmt;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-pub type CardColumn = Vec<Card>;
+use super::error::ParseError;
+use super::rules::*;
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Card {
@@ -13,8 +15,12 @@ pub struct Card {
 
 impl Card {
     // true if this card can legally stack on top of "base" on the tableau
-    pub fn stacks_on(&self, base: &Card) -> bool {
-        self.suit.colour() != base.suit.colour() && base.rank == self.rank + 1
+    pub fn stacks_on(&self, base: &Card, rules: &Rules) -> bool {
+        let suits_fit = match rules.building {
+            Building::AlternateColours => self.suit.colour() != base.suit.colour(),
+            Building::SameSuit => self.suit == base.suit,
+        };
+        suits_fit && base.rank == self.rank + 1
     }
 
     // true if this card can legally go on the foundation with the card "base" on top
@@ -46,6 +52,36 @@ impl fmt::Display for Card {
     }
 }
 
+// reads cards as they're displayed ("10♥", "1♠", "Q♦") or written in ascii ("TH", "AS", "qd")
+impl FromStr for Card {
+    type Err = ParseError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let bad = || ParseError::BadCard {
+            text: s.to_string(),
+        };
+        let mut chars = s.chars();
+        let suit = match chars.next_back().ok_or_else(bad)?.to_ascii_uppercase() {
+            '♣' | 'C' => Suit::Clubs,
+            '♦' | 'D' => Suit::Diamonds,
+            '♥' | 'H' => Suit::Hearts,
+            '♠' | 'S' => Suit::Spades,
+            _ => return Err(bad()),
+        };
+        let rank = match chars.as_str().to_ascii_uppercase().as_str() {
+            "A" => 1,
+            "T" => 10,
+            "J" => 11,
+            "Q" => 12,
+            "K" => 13,
+            n => match n.parse() {
+                Ok(n @ 1..=10) => n,
+                _ => return Err(bad()),
+            },
+        };
+        Ok(Card::new(rank, suit))
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Suit {
     Clubs,
@@ -107,7 +143,7 @@ impl fmt::Display for Suit {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CardAddress {
     Column(usize),
     Foundation(Suit),
@@ -123,3 +159,21 @@ impl fmt::Display for CardAddress {
         }
     }
 }
+
+// a single move of one or more cards from one place to another
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub from: CardAddress,
+    pub to: CardAddress,
+    pub count: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count == 1 {
+            write!(f, "{} to {}", self.from, self.to)
+        } else {
+            write!(f, "{} cards from {} to {}", self.count, self.from, self.to)
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::board::*;
+use super::error::BoardError;
+use super::rules::*;
+
+// the highest deal number in the original Microsoft FreeCell
+const MICROSOFT_DEAL_COUNT: u32 = 32000;
+
+// which generator shuffled a game, and the number it was given
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Deal {
+    // shuffled with ChaCha from a seed
+    Seed(u64),
+    // one of the numbered Microsoft FreeCell deals
+    Microsoft(u32),
+}
+
+impl Deal {
+    // shuffle & create the game for this deal, laid out for these rules
+    pub fn new_game(&self, rules: &Rules) -> Result<Board, BoardError> {
+        match *self {
+            Deal::Seed(seed) => Board::new_game(seed, rules),
+            Deal::Microsoft(number) => Board::new_microsoft_game(number, rules),
+        }
+    }
+
+    // a random deal from the same generator as this one
+    pub fn random_like<R: Rng>(&self, rng: &mut R) -> Self {
+        match self {
+            Deal::Seed(_) => Deal::Seed(rng.gen()),
+            Deal::Microsoft(_) => Deal::Microsoft(rng.gen_range(1..=MICROSOFT_DEAL_COUNT)),
+        }
+    }
+
+    // what the player passes to replay the deal: a seed goes straight to `-s`, and a
+    // microsoft deal needs its flag, since the same number as a seed is a different deal
+    pub fn replay_text(&self) -> String {
+        match *self {
+            Deal::Seed(seed) => seed.to_string(),
+            Deal::Microsoft(number) => format!("--ms-deal {}", number),
+        }
+    }
+}
+
+impl fmt::Display for Deal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Deal::Seed(seed) => write!(f, "seed: {}", seed),
+            Deal::Microsoft(number) => write!(f, "deal: #{}", number),
+        }
+    }
+}
@@ -1,27 +1,268 @@
+use std::fmt;
+
 use thiserror::Error;
 
 use super::card::*;
+use super::rules::*;
 
 pub type Result<T> = std::result::Result<T, MoveError>;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum MoveError {
     #[error("cannot move current cards to {to}: {reason}")]
-    CannotPlace { reason: String, to: CardAddress },
+    CannotPlace {
+        reason: MoveRejection,
+        to: CardAddress,
+    },
     #[error("cannot pick up cards from {from}: {reason}")]
-    CannotPickUp { reason: String, from: CardAddress },
+    CannotPickUp {
+        reason: MoveRejection,
+        from: CardAddress,
+    },
     #[error("address {address} does not exist on the board")]
     IllegalAddress { address: CardAddress },
+    #[error("cannot read move {text:?}: {reason}")]
+    BadNotation { text: String, reason: NotationError },
+}
+
+// why the board refused to pick up or place cards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveRejection {
+    AlreadyHolding,
+    EmptyAddress,
+    OffFoundation,
+    EmptyStack,
+    StackOnlyFromColumn,
+    // only the top `position` cards of the stack are in sequence
+    UnsoundStack {
+        position: usize,
+    },
+    // moving more cards than the free cells & empty columns allow
+    StackTooLarge {
+        limit: usize,
+        free_cells: usize,
+        empty_columns: usize,
+    },
+    StackLargerThanColumn {
+        column_size: usize,
+    },
+    // `onto` is the card on top of the column or foundation, which has rank 0 when it's empty
+    DoesNotFit {
+        card: Card,
+        onto: Card,
+    },
+    // the rules only allow kings into empty columns
+    KingsOnly {
+        card: Card,
+    },
+    CellOccupied,
+    SingleCardsOnly,
+    NoCardsHeld,
+}
+
+impl fmt::Display for MoveRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveRejection::AlreadyHolding => write!(f, "already holding cards"),
+            MoveRejection::EmptyAddress => write!(f, "empty address"),
+            MoveRejection::OffFoundation => write!(f, "cannot move off foundation"),
+            MoveRejection::EmptyStack => write!(f, "cannot pick up zero-card stack"),
+            MoveRejection::StackOnlyFromColumn => {
+                write!(f, "cannot pick up a stack from anywhere except a column")
+            }
+            MoveRejection::UnsoundStack { position } => {
+                write!(f, "only the top {} in sequence", plural(*position, "card"))
+            }
+            MoveRejection::StackTooLarge {
+                limit,
+                free_cells,
+                empty_columns,
+            } => {
+                write!(
+                    f,
+                    "can only move {} with {}",
+                    plural(*limit, "card"),
+                    plural(*free_cells, "free cell")
+                )?;
+                if *empty_columns > 0 {
+                    write!(f, " and {}", plural(*empty_columns, "empty column"))?;
+                }
+                Ok(())
+            }
+            MoveRejection::StackLargerThanColumn { column_size } => write!(
+                f,
+                "there {} in that column",
+                match column_size {
+                    1 => "is only 1 card".to_string(),
+                    n => format!("are only {} cards", n),
+                }
+            ),
+            MoveRejection::DoesNotFit { card, onto } if onto.rank == 0 => {
+                write!(f, "{} can't start a foundation", card)
+            }
+            MoveRejection::DoesNotFit { card, onto } => {
+                write!(f, "{} does not go on {}", card, onto)
+            }
+            MoveRejection::KingsOnly { card } => {
+                write!(f, "{} can't go in an empty column, only kings can", card)
+            }
+            MoveRejection::CellOccupied => write!(f, "that free cell is taken"),
+            MoveRejection::SingleCardsOnly => write!(f, "only one card can go there at once"),
+            MoveRejection::NoCardsHeld => write!(f, "cannot place cards when not holding cards"),
+        }
+    }
+}
+
+// a number of things, as in "1 card" or "2 cards"
+fn plural(n: usize, thing: &str) -> String {
+    if n == 1 {
+        format!("1 {}", thing)
+    } else {
+        format!("{} {}s", n, thing)
+    }
+}
+
+// why a move written in standard notation couldn't be read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NotationError {
+    TooShort,
+    FromFoundation,
+    // the symbol isn't a column or a free cell
+    BadSource { symbol: char },
+    BadDestination { symbol: char },
+    BadCount,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::TooShort => write!(f, "a move needs a source and a destination"),
+            NotationError::FromFoundation => write!(f, "cannot move cards off the foundations"),
+            NotationError::BadSource { symbol } => write!(f, "unknown source {:?}", symbol),
+            NotationError::BadDestination { symbol } => {
+                write!(f, "unknown destination {:?}", symbol)
+            }
+            NotationError::BadCount => write!(f, "the number of cards must be a number"),
+        }
+    }
+}
+
+// why a position written out as text couldn't be read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayoutError {
+    Empty,
+    // the top line has more slots than free cells & foundations
+    TooManyCells,
+    FoundationSuit,
+    TooManyColumns,
+    // held cards have to be on the last line
+    BadFloating,
+    Gap,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Empty => write!(f, "there is nothing to read"),
+            LayoutError::TooManyCells => {
+                write!(f, "more free cells and foundations than the board has")
+            }
+            LayoutError::FoundationSuit => write!(f, "foundations must go in the order ♣ ♦ ♥ ♠"),
+            LayoutError::TooManyColumns => write!(f, "more columns than the board has"),
+            LayoutError::BadFloating => write!(f, "held cards must come after the columns"),
+            LayoutError::Gap => write!(f, "a column cannot have gaps in it"),
+        }
+    }
+}
+
+// why a position code couldn't be read
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CodeError {
+    TooShort,
+    TooLong,
+    Version,
+    Rules { reason: RulesError },
+    // not one of the base 64 digits
+    Character,
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodeError::TooShort => write!(f, "it stops part-way through"),
+            CodeError::TooLong => write!(f, "there is more after the end of the position"),
+            CodeError::Version => write!(f, "it was written by a different version of the game"),
+            CodeError::Rules { reason } => write!(f, "its rules can't be played: {}", reason),
+            CodeError::Character => write!(f, "it has a character that is not in base 64"),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParseError {
+    #[error("{text:?} is not a card")]
+    BadCard { text: String },
+    #[error("line {line}: {reason}")]
+    BadLayout { line: usize, reason: LayoutError },
+    #[error("not a position code: {reason}")]
+    BadCode { reason: CodeError },
+    #[error("not a legal position: {0}")]
+    IllegalPosition(#[from] BoardError),
+}
+
+// why a game can't be played under a set of rules
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RulesError {
+    NoColumns,
+    TooManyColumns,
+    TooManyFreeCells,
+    // more cards are dealt to the free cells than there are free cells
+    TooManyDealt,
+    Decks,
 }
 
-pub const REASON_ALREADY_HOLDING: &str = "already holding cards";
-pub const REASON_EMPTY_ADDRESS: &str = "empty address";
-pub const REASON_MOVE_FOUNDATION: &str = "cannot move off foundation";
-pub const REASON_EMPTY_STACK: &str = "cannot pick up zero-card stack";
-pub const REASON_UNSOUND_STACK: &str = "cards in stack don't stack";
-pub const REASON_STACK_TOO_LARGE: &str = "cannot pick up that many cards at once";
-pub const REASON_STACK_LARGER_THAN_COLUMN: &str = "there are not that many cards in that column";
-pub const REASON_DOES_NOT_FIT: &str = "those cards do not fit there";
-pub const REASON_NO_CARDS_HELD: &str = "cannot place cards when not holding cards";
-pub const REASON_CAN_ONLY_GET_STACK_FROM_COLUMN: &str =
-    "cannot pick up a stack from anywhere except a column";
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::NoColumns => write!(f, "there are no columns"),
+            RulesError::TooManyColumns => {
+                write!(f, "there can be at most {} columns", MAX_COLUMNS)
+            }
+            RulesError::TooManyFreeCells => {
+                write!(f, "there can be at most {} free cells", MAX_FREE_CELLS)
+            }
+            RulesError::TooManyDealt => {
+                write!(
+                    f,
+                    "more cards are dealt to the free cells than there are free cells"
+                )
+            }
+            RulesError::Decks => write!(f, "there must be between 1 and {} decks", MAX_DECKS),
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum BoardError {
+    #[error("the rules can't be played: {reason}")]
+    BadRules { reason: RulesError },
+    #[error("the columns and free cells don't match the rules")]
+    WrongShape,
+    #[error("the {card} is missing")]
+    MissingCard { card: Card },
+    #[error("the {card} is there {count} times")]
+    DuplicateCard { card: Card, count: usize },
+    // the card can't be displayed, since it doesn't exist
+    #[error("there is a {suit:?} card with rank {rank}")]
+    BadRank { rank: u8, suit: Suit },
+    #[error("the foundations must be one of each suit, in the order ♣ ♦ ♥ ♠")]
+    BadFoundations,
+    #[error("holding a single card and a stack at once")]
+    HoldingTwice,
+    #[error("the held stack came from column {column}, which is not there")]
+    BadStackSource { column: usize },
+    #[error("the board's view does not match its state")]
+    StaleView,
+    #[error("a move in the history cannot be made from the position before it")]
+    BadHistory,
+}
@@ -1,11 +1,19 @@
 mod board;
 mod card;
+mod deal;
 mod error;
+mod notation;
+mod rules;
 mod save_load;
+mod solver;
 mod undo;
 
-pub use board::{Board, BoardView};
-pub use card::{Card, CardAddress, Suit};
-pub use error::{MoveError, Result};
+pub use board::{AutoMovePolicy, Board, BoardView};
+pub use card::{Card, CardAddress, Move, Suit};
+pub use deal::Deal;
+pub use error::{MoveError, MoveRejection, Result};
+pub use notation::*;
+pub use rules::*;
 pub use save_load::*;
+pub use solver::*;
 pub use undo::*;
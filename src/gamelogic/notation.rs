@@ -0,0 +1,278 @@
+// the move notation used by FreeCell solvers & forums.
+// columns are 1-8, free cells are a-d, and the foundations are h, so "3a" moves the
+// last card of the third column into the first free cell.
+// variants with more columns & free cells carry on with a tenth column of 0, and with the
+// free cells skipping over h, so that the eighth is i.
+// the number of cards moved between columns is normally implied, since only one number
+// of cards can go onto a given card. when a move doesn't move the usual number of cards
+// (e.g. moving only part of a stack into an empty column), the count is written in front,
+// as in "236" for moving two cards from the third column to the sixth
+
+use super::board::*;
+use super::card::*;
+use super::error::*;
+
+// read a single move made from this position.
+// the move is checked against the board, and illegal moves are rejected
+pub fn parse_move(board: &Board, text: &str) -> Result<Move> {
+    let bad = |reason| MoveError::BadNotation {
+        text: text.to_string(),
+        reason,
+    };
+    let chars: Vec<char> = text.trim().chars().collect();
+    if chars.len() < 2 {
+        return Err(bad(NotationError::TooShort));
+    }
+    let (count_chars, ends) = chars.split_at(chars.len() - 2);
+
+    let from = match ends[0] {
+        'h' => return Err(bad(NotationError::FromFoundation)),
+        c => parse_address(c).ok_or_else(|| bad(NotationError::BadSource { symbol: c }))?,
+    };
+    let to = match ends[1] {
+        // there's one symbol for every foundation, so use the one for the card being moved
+        'h' => {
+            let view = board.view();
+            let card = match from {
+                CardAddress::Column(n) => view.columns.get(n).and_then(|c| c.last().copied()),
+                CardAddress::FreeCell(n) => view.free_cells.get(n).copied().flatten(),
+                CardAddress::Foundation(_) => None,
+            };
+            match card {
+                Some(card) => CardAddress::Foundation(card.suit),
+                None => {
+                    return Err(MoveError::CannotPickUp {
+                        from,
+                        reason: MoveRejection::EmptyAddress,
+                    })
+                }
+            }
+        }
+        c => parse_address(c).ok_or_else(|| bad(NotationError::BadDestination { symbol: c }))?,
+    };
+    let count = if count_chars.is_empty() {
+        implied_count(board, from, to)
+    } else {
+        count_chars
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|_| bad(NotationError::BadCount))?
+    };
+
+    let mv = Move { from, to, count };
+    board.apply(&mv)?;
+    Ok(mv)
+}
+
+// read a series of moves separated by spaces or commas, starting from this position
+pub fn parse_moves(board: &Board, text: &str) -> Result<Vec<Move>> {
+    let mut board = board.clone();
+    let mut moves = Vec::new();
+    for word in text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+    {
+        let mv = parse_move(&board, word)?;
+        board = board.apply(&mv)?;
+        moves.push(mv);
+    }
+    Ok(moves)
+}
+
+// write a move made from this position
+pub fn format_move(board: &Board, mv: &Move) -> String {
+    let count = if mv.count == implied_count(board, mv.from, mv.to) {
+        String::new()
+    } else {
+        mv.count.to_string()
+    };
+    format!(
+        "{}{}{}",
+        count,
+        format_address(mv.from),
+        format_address(mv.to)
+    )
+}
+
+// write a series of moves, starting from this position.
+// stops at the first illegal move
+pub fn format_moves(board: &Board, moves: &[Move]) -> String {
+    let mut board = board.clone();
+    let mut words = Vec::new();
+    for mv in moves {
+        words.push(format_move(&board, mv));
+        board = match board.apply(mv) {
+            Ok(next) => next,
+            Err(_) => break,
+        };
+    }
+    words.join(" ")
+}
+
+// the number of cards a move between these addresses means when no count is written:
+// the most cards that can legally make the move, or one card if it can't be made
+fn implied_count(board: &Board, from: CardAddress, to: CardAddress) -> usize {
+    if let (CardAddress::Column(n), CardAddress::Column(_)) = (from, to) {
+        let column_len = board.view().columns.get(n).map_or(0, |c| c.len());
+        (1..=column_len)
+            .rev()
+            .find(|&count| board.is_legal(&Move { from, to, count }))
+            .unwrap_or(1)
+    } else {
+        1
+    }
+}
+
+fn parse_address(c: char) -> Option<CardAddress> {
+    match c {
+        '1'..='9' => Some(CardAddress::Column(c as usize - '1' as usize)),
+        '0' => Some(CardAddress::Column(9)),
+        'a'..='g' => Some(CardAddress::FreeCell(c as usize - 'a' as usize)),
+        'i' => Some(CardAddress::FreeCell(7)),
+        _ => None,
+    }
+}
+
+fn format_address(address: CardAddress) -> String {
+    match address {
+        CardAddress::Column(9) => "0".to_string(),
+        CardAddress::Column(n) => (n + 1).to_string(),
+        CardAddress::FreeCell(7) => "i".to_string(),
+        CardAddress::FreeCell(n) => ((b'a' + n as u8) as char).to_string(),
+        CardAddress::Foundation(_) => "h".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::inspect::*;
+    use super::super::rules::Rules;
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let game = board_from_columns(vec![
+            vec![Card::new(3, Suit::Clubs), Card::new(1, Suit::Hearts)],
+            vec![
+                Card::new(4, Suit::Diamonds),
+                Card::new(3, Suit::Spades),
+                Card::new(2, Suit::Hearts),
+            ],
+            Vec::new(),
+        ]);
+        assert_eq!(
+            parse_move(&game, "1h"),
+            Ok(Move {
+                from: CardAddress::Column(0),
+                to: CardAddress::Foundation(Suit::Hearts),
+                count: 1,
+            })
+        );
+        assert_eq!(
+            parse_move(&game, "1b"),
+            Ok(Move {
+                from: CardAddress::Column(0),
+                to: CardAddress::FreeCell(1),
+                count: 1,
+            })
+        );
+        // as many cards as possible go into the empty column
+        assert_eq!(
+            parse_move(&game, "23"),
+            Ok(Move {
+                from: CardAddress::Column(1),
+                to: CardAddress::Column(2),
+                count: 3,
+            })
+        );
+        assert_eq!(
+            parse_move(&game, "223"),
+            Ok(Move {
+                from: CardAddress::Column(1),
+                to: CardAddress::Column(2),
+                count: 2,
+            })
+        );
+        let moves = parse_moves(&game, "1h, 1a 21").unwrap();
+        assert_eq!(moves.len(), 3);
+        assert_eq!(moves[2].count, 3);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let game = board_from_columns(vec![
+            vec![Card::new(3, Suit::Clubs), Card::new(1, Suit::Hearts)],
+            Vec::new(),
+        ]);
+        assert_eq!(
+            parse_move(&game, "1"),
+            Err(MoveError::BadNotation {
+                text: "1".to_string(),
+                reason: NotationError::TooShort,
+            })
+        );
+        assert_eq!(
+            parse_move(&game, "1z"),
+            Err(MoveError::BadNotation {
+                text: "1z".to_string(),
+                reason: NotationError::BadDestination { symbol: 'z' },
+            })
+        );
+        assert_eq!(
+            parse_move(&game, "x12"),
+            Err(MoveError::BadNotation {
+                text: "x12".to_string(),
+                reason: NotationError::BadCount,
+            })
+        );
+        assert_eq!(
+            parse_move(&game, "2h"),
+            Err(MoveError::CannotPickUp {
+                from: CardAddress::Column(1),
+                reason: MoveRejection::EmptyAddress,
+            })
+        );
+        assert_eq!(
+            parse_move(&game, "a1"),
+            Err(MoveError::CannotPickUp {
+                from: CardAddress::FreeCell(0),
+                reason: MoveRejection::EmptyAddress,
+            })
+        );
+        assert_eq!(
+            parse_moves(&game, "1a 1b 1c"),
+            Err(MoveError::CannotPickUp {
+                from: CardAddress::Column(0),
+                reason: MoveRejection::EmptyAddress,
+            })
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for seed in 0..5 {
+            let mut game = Board::new_game(seed, &Rules::default()).unwrap();
+            // play a few moves so that free cells & empty columns get involved
+            for _ in 0..10 {
+                for mv in game.legal_moves() {
+                    let text = format_move(&game, &mv);
+                    assert_eq!(parse_move(&game, &text), Ok(mv), "{}", text);
+                }
+                if let Some(mv) = game.legal_moves().last() {
+                    game = game.apply(mv).unwrap();
+                }
+            }
+        }
+        for rules in &[Rules::eight_off(), Rules::seahaven_towers()] {
+            let game = Board::new_game(0, rules).unwrap();
+            for mv in game.legal_moves() {
+                let text = format_move(&game, &mv);
+                assert_eq!(parse_move(&game, &text), Ok(mv), "{}", text);
+            }
+        }
+        let game = Board::new_game(0, &Rules::default()).unwrap();
+        let moves: Vec<Move> = game.legal_moves().into_iter().take(1).collect();
+        assert_eq!(parse_moves(&game, &format_moves(&game, &moves)), Ok(moves));
+    }
+}
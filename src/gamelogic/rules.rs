@@ -0,0 +1,235 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::RulesError;
+
+// the most of each thing there's room for on the board
+pub const MAX_COLUMNS: usize = 10;
+pub const MAX_FREE_CELLS: usize = 8;
+pub const MAX_DECKS: usize = 2;
+
+// how cards build down on each other in the columns
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Building {
+    AlternateColours,
+    SameSuit,
+}
+
+// which cards can be moved into an empty column
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EmptyColumns {
+    AnyCard,
+    KingsOnly,
+}
+
+// the rules of the game being played, which make the difference between FreeCell
+// and its variants. saved with the game, so that it's replayed under the same rules
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    pub columns: usize,
+    pub free_cells: usize,
+    // how many free cells get a card in the deal, instead of starting empty
+    pub dealt_to_free_cells: usize,
+    pub building: Building,
+    pub empty_columns: EmptyColumns,
+    // if true, a stack can only be as big as the free cells & empty columns could move
+    // one card at a time. if false, any stack in sequence can move at once
+    pub limited_stacks: bool,
+    // how many full decks are shuffled together. each suit gets a foundation per deck
+    #[serde(default = "one_deck")]
+    pub decks: usize,
+}
+
+fn one_deck() -> usize {
+    1
+}
+
+// the variants that can be picked by name
+pub const VARIANT_NAMES: &[&str] = &[
+    "freecell",
+    "bakers-game",
+    "eight-off",
+    "seahaven",
+    "relaxed",
+    "double-deck",
+];
+
+impl Rules {
+    pub fn freecell() -> Self {
+        Rules {
+            columns: 8,
+            free_cells: 4,
+            dealt_to_free_cells: 0,
+            building: Building::AlternateColours,
+            empty_columns: EmptyColumns::AnyCard,
+            limited_stacks: true,
+            decks: 1,
+        }
+    }
+
+    // FreeCell, but building down in the same suit
+    pub fn bakers_game() -> Self {
+        Rules {
+            building: Building::SameSuit,
+            ..Rules::freecell()
+        }
+    }
+
+    // eight free cells, four of which are dealt cards, and only kings can fill a column
+    pub fn eight_off() -> Self {
+        Rules {
+            free_cells: 8,
+            dealt_to_free_cells: 4,
+            building: Building::SameSuit,
+            empty_columns: EmptyColumns::KingsOnly,
+            ..Rules::freecell()
+        }
+    }
+
+    // ten columns of five, with the last two cards dealt into the free cells
+    pub fn seahaven_towers() -> Self {
+        Rules {
+            columns: 10,
+            dealt_to_free_cells: 2,
+            building: Building::SameSuit,
+            empty_columns: EmptyColumns::KingsOnly,
+            ..Rules::freecell()
+        }
+    }
+
+    // FreeCell, but any stack in sequence can be moved at once
+    pub fn relaxed() -> Self {
+        Rules {
+            limited_stacks: false,
+            ..Rules::freecell()
+        }
+    }
+
+    // FreeCell with two decks, dealt into ten columns with six free cells
+    pub fn double_deck() -> Self {
+        Rules {
+            columns: 10,
+            free_cells: 6,
+            decks: 2,
+            ..Rules::freecell()
+        }
+    }
+
+    pub fn foundations(&self) -> usize {
+        4 * self.decks
+    }
+
+    // whether a game can be laid out & played under these rules
+    pub fn check(&self) -> std::result::Result<(), RulesError> {
+        if self.columns == 0 {
+            Err(RulesError::NoColumns)
+        } else if self.columns > MAX_COLUMNS {
+            Err(RulesError::TooManyColumns)
+        } else if self.free_cells > MAX_FREE_CELLS {
+            Err(RulesError::TooManyFreeCells)
+        } else if self.dealt_to_free_cells > self.free_cells {
+            Err(RulesError::TooManyDealt)
+        } else if self.decks == 0 || self.decks > MAX_DECKS {
+            Err(RulesError::Decks)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::freecell()
+    }
+}
+
+impl FromStr for Rules {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "freecell" => Ok(Rules::freecell()),
+            "bakers-game" => Ok(Rules::bakers_game()),
+            "eight-off" => Ok(Rules::eight_off()),
+            "seahaven" => Ok(Rules::seahaven_towers()),
+            "relaxed" => Ok(Rules::relaxed()),
+            "double-deck" => Ok(Rules::double_deck()),
+            _ => Err(format!(
+                "unknown variant {:?}, expected one of: {}",
+                s,
+                VARIANT_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // a custom number of free cells is shown as a change to the variant it was made from
+        let variant = VARIANT_NAMES.iter().find_map(|name| {
+            let rules: Rules = name.parse().unwrap();
+            let same_but_cells = Rules {
+                free_cells: self.free_cells,
+                ..rules
+            };
+            if same_but_cells == *self {
+                Some((name, rules))
+            } else {
+                None
+            }
+        });
+        match variant {
+            Some((name, rules)) if rules.free_cells == self.free_cells => write!(f, "{}", name),
+            Some((name, _)) => write!(f, "{} with {} free cells", name, self.free_cells),
+            None => write!(f, "custom rules"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for name in VARIANT_NAMES {
+            let rules: Rules = name.parse().unwrap();
+            assert_eq!(&rules.to_string(), name);
+        }
+        assert_eq!("Eight-Off".parse(), Ok(Rules::eight_off()));
+        assert!("klondike".parse::<Rules>().is_err());
+        let rules = Rules {
+            free_cells: 6,
+            ..Rules::bakers_game()
+        };
+        assert_eq!(rules.to_string(), "bakers-game with 6 free cells");
+        let rules = Rules {
+            columns: 9,
+            ..Rules::freecell()
+        };
+        assert_eq!(rules.to_string(), "custom rules");
+    }
+
+    #[test]
+    fn checks() {
+        for name in VARIANT_NAMES {
+            assert_eq!(name.parse::<Rules>().unwrap().check(), Ok(()));
+        }
+        let too_many_dealt = Rules {
+            free_cells: 2,
+            ..Rules::eight_off()
+        };
+        assert_eq!(too_many_dealt.check(), Err(RulesError::TooManyDealt));
+        let no_columns = Rules {
+            columns: 0,
+            ..Rules::freecell()
+        };
+        assert_eq!(no_columns.check(), Err(RulesError::NoColumns));
+        let no_decks = Rules {
+            decks: 0,
+            ..Rules::freecell()
+        };
+        assert_eq!(no_decks.check(), Err(RulesError::Decks));
+    }
+}
@@ -1,36 +1,564 @@
-use std::fs;
-use std::io::Write;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use super::board::inspect::*;
 use super::*;
 
+// the version of the save format written by this version of the game.
+// saves from before there was a version are format 0
+const SAVE_FORMAT: u64 = 1;
+
+// the extension of files in the saves directory
+const SAVE_EXTENSION: &str = "json";
+
+// what's written to a save file. positions are stored as position codes and the history
+// as moves, so that saves don't depend on how boards or the history are kept in memory
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    format: u64,
+    // the version of the game that wrote the save, for explaining saves that can't be read
+    game_version: String,
+    // none for a game started from a position code
+    deal: Option<Deal>,
+    // also in the position code, and checked against it when loading
+    rules: Rules,
+    // in seconds since the unix epoch
+    created: u64,
+    updated: u64,
+    board: String,
+    undo: UndoRecord,
+}
+
+// older saves only stored a bare seed, since every game came from a seed back then
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedDeal {
+    Deal(Deal),
+    Seed(u64),
+}
+
+// a game as it's saved & loaded
+#[derive(Clone, Debug)]
+pub struct SavedGame {
+    // none for a game started from a position code
+    pub deal: Option<Deal>,
+    pub board: Board,
+    pub undo: BoardUndoStack,
+    // when the game was first saved, if it has been, in seconds since the unix epoch
+    pub created: Option<u64>,
+    // the name it's saved under in the saves directory, if it's been saved there
+    pub name: Option<String>,
+}
+
+// a save in the saves directory, as it's listed
+pub struct SaveListing {
+    pub name: String,
+    // when the game was last saved, in seconds since the unix epoch
+    pub updated: u64,
+    pub game: Result<SavedGame>,
+}
+
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// a time in seconds since the unix epoch as a date & time in utc
+pub fn format_time(time: u64) -> String {
+    let (year, month, day, hour, minute, second) = date_time(time);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year, month, day, hour, minute, second
+    )
+}
+
+// the year, month, day, hour, minute and second of a time, from howard hinnant's
+// civil_from_days. days are counted from the 1st of march, so leap days come last
+fn date_time(time: u64) -> (u64, u64, u64, u64, u64, u64) {
+    let (days, seconds) = (time / 86_400, time % 86_400);
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    )
+}
+
+// the directory saves go in: the game's folder in the per-user data directory
+pub fn saves_dir() -> Result<PathBuf> {
+    let data_dir =
+        data_dir().ok_or_else(|| anyhow!("couldn't find a data directory to keep saves in"))?;
+    Ok(data_dir.join("freecell").join("saves"))
+}
+
+#[cfg(windows)]
+fn data_dir() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(PathBuf::from)
+}
+
+#[cfg(target_os = "macos")]
+fn data_dir() -> Option<PathBuf> {
+    let home = PathBuf::from(env::var_os("HOME")?);
+    Some(home.join("Library").join("Application Support"))
+}
+
+// $XDG_DATA_HOME, which defaults to ~/.local/share. relative paths are meant to be ignored
+#[cfg(not(any(windows, target_os = "macos")))]
+fn data_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_DATA_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Some(dir),
+        _ => {
+            let home = PathBuf::from(env::var_os("HOME")?);
+            Some(home.join(".local").join("share"))
+        }
+    }
+}
+
+// find a save given on the command line, which is either a path to a save file or
+// the name of a save in the saves directory
+pub fn find_save(name: &Path) -> Result<PathBuf> {
+    // a path doesn't need the saves directory, which might not be there to find
+    if name.is_file() {
+        return Ok(name.to_path_buf());
+    }
+    find_named_save(&saves_dir()?, name)
+}
+
+fn find_named_save(dir: &Path, name: &Path) -> Result<PathBuf> {
+    let filename = dir.join(format!("{}.{}", name.display(), SAVE_EXTENSION));
+    if filename.is_file() {
+        Ok(filename)
+    } else {
+        Err(anyhow!(
+            "there's no save file at {:?} and no save named {:?} in {:?}",
+            name,
+            name,
+            dir
+        ))
+    }
+}
+
+// the name of a save file, if it's in the saves directory
+pub fn save_name_of(filename: &Path) -> Option<String> {
+    let dir = saves_dir().ok()?;
+    if filename.parent() != Some(dir.as_path())
+        || filename.extension().and_then(|e| e.to_str()) != Some(SAVE_EXTENSION)
+    {
+        return None;
+    }
+    Some(filename.file_stem()?.to_str()?.to_string())
+}
+
+// every save in the saves directory, most recently saved first.
+// saves that can't be loaded are listed too, with why they can't be
+pub fn list_saves(dir: &Path) -> Result<Vec<SaveListing>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut saves = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let filename = entry?.path();
+        if filename.extension().and_then(|e| e.to_str()) != Some(SAVE_EXTENSION) {
+            continue;
+        }
+        let name = match filename.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        saves.push(match read(&filename) {
+            Ok((game, updated)) => SaveListing {
+                game: Ok(SavedGame {
+                    name: Some(name.clone()),
+                    ..game
+                }),
+                name,
+                updated,
+            },
+            Err(e) => SaveListing {
+                name,
+                updated: modified_time(&filename)?,
+                game: Err(e),
+            },
+        });
+    }
+    saves.sort_by(|a, b| b.updated.cmp(&a.updated).then(a.name.cmp(&b.name)));
+    Ok(saves)
+}
+
+fn modified_time(filename: &Path) -> Result<u64> {
+    Ok(fs::metadata(filename)?.modified().map_or(0, unix_time))
+}
+
 // load game
-pub fn load(filename: &Path) -> Result<(u64, Board, BoardUndoStack)> {
-    let save = fs::read_to_string(filename)?;
-    let (seed, state, undo): (u64, StateContainer, BoardUndoStack) = serde_json::from_str(&save)?;
-    Ok((seed, board_from_state(state), undo))
-}
-
-// save game
-pub fn save(
-    seed: u64,
-    game: &Board,
-    undo: &BoardUndoStack,
-    dir: PathBuf,
-    name: &str,
-) -> Result<PathBuf> {
-    let save = serde_json::to_string(&(seed, board_get_state(game), undo))?;
+pub fn load(filename: &Path) -> Result<SavedGame> {
+    Ok(read(filename)?.0)
+}
+
+// read a save file, along with when it was last saved
+fn read(filename: &Path) -> Result<(SavedGame, u64)> {
+    let save: Value = serde_json::from_str(&fs::read_to_string(filename)?)?;
+    let save = match save.get("format").and_then(Value::as_u64) {
+        Some(SAVE_FORMAT) => serde_json::from_value(save)?,
+        Some(format) => {
+            let game_version = save.get("game_version").and_then(Value::as_str);
+            return Err(anyhow!(
+                "the save is in format {}, from version {} of the game, but only format {} can be read",
+                format,
+                game_version.unwrap_or("unknown"),
+                SAVE_FORMAT
+            ));
+        }
+        None => {
+            // the file's last change is the closest thing to a timestamp that old saves have
+            migrate_from_tuple(save, modified_time(filename)?)?
+        }
+    };
+    // the save could have been edited or cut off, so don't trust what's in it
+    let board = Board::from_code(&save.board).context("the saved game is not a legal position")?;
+    if board.rules() != &save.rules {
+        return Err(anyhow!(
+            "the saved game is {} but its position is laid out for {}",
+            save.rules,
+            board.rules()
+        ));
+    }
+    let undo = BoardUndoStack::from_record(save.undo)
+        .context("the saved undo history has an illegal position in it")?;
+    undo.validate()
+        .context("the saved undo history has an illegal position in it")?;
+    let game = SavedGame {
+        deal: save.deal,
+        board,
+        undo,
+        created: Some(save.created),
+        name: None,
+    };
+    Ok((game, save.updated))
+}
+
+// format 0 was a bare tuple of the deal, the board's internal state, and the undo history
+fn migrate_from_tuple(save: Value, modified: u64) -> Result<SaveFile> {
+    let (deal, state, undo): (SavedDeal, StateContainer, BoardUndoStack) =
+        serde_json::from_value(save)?;
+    let deal = match deal {
+        SavedDeal::Deal(deal) => deal,
+        SavedDeal::Seed(seed) => Deal::Seed(seed),
+    };
+    let board = board_from_state(state);
+    board
+        .validate()
+        .context("the saved game is not a legal position")?;
+    Ok(SaveFile {
+        format: SAVE_FORMAT,
+        game_version: "unknown".to_string(),
+        deal: Some(deal),
+        rules: *board.rules(),
+        created: modified,
+        updated: modified,
+        board: board.to_code(),
+        undo: undo.to_record(),
+    })
+}
+
+// save game to the saves directory. a game with a name replaces its earlier save, and
+// otherwise the save is named after the time it was made
+pub fn save(game: &SavedGame, dir: &Path) -> Result<PathBuf> {
+    let now = unix_time(SystemTime::now());
+    let save = serde_json::to_string(&SaveFile {
+        format: SAVE_FORMAT,
+        game_version: env!("CARGO_PKG_VERSION").to_string(),
+        deal: game.deal,
+        rules: *game.board.rules(),
+        created: game.created.unwrap_or(now),
+        updated: now,
+        board: game.board.to_code(),
+        undo: game.undo.to_record(),
+    })?;
+    fs::create_dir_all(dir)?;
+    if let Some(name) = &game.name {
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(anyhow!("{:?} can't be used as the name of a save", name));
+        }
+        let filename = dir.join(format!("{}.{}", name, SAVE_EXTENSION));
+        fs::write(&filename, save)?;
+        return Ok(filename);
+    }
+    // there's a number on the end if there was already a save made in the same second
+    let timestamp = format_time(now).replace(' ', "_").replace(':', "-");
     for n in 0.. {
-        let mut filename = dir.clone();
-        filename.push(name.to_string() + &n.to_string());
-        if !filename.exists() {
-            let mut file = fs::File::create(filename.clone())?;
-            file.write_all(save.as_bytes())?;
-            return Ok(filename);
+        let name = match n {
+            0 => timestamp.clone(),
+            n => format!("{}-{}", timestamp, n),
+        };
+        let filename = dir.join(format!("{}.{}", name, SAVE_EXTENSION));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&filename)
+        {
+            Ok(mut file) => {
+                file.write_all(save.as_bytes())?;
+                return Ok(filename);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
         }
     }
     unreachable!();
 }
+
+#[cfg(test)]
+mod test {
+    use super::super::error::BoardError;
+    use super::*;
+
+    #[test]
+    fn save_and_load_deal() {
+        let dir = env::temp_dir().join("freecell_test.deal");
+        let deal = Deal::Microsoft(11982);
+        let game = deal.new_game(&Rules::seahaven_towers()).unwrap();
+        let saved = SavedGame {
+            deal: Some(deal),
+            board: game.clone(),
+            undo: BoardUndoStack::new(),
+            created: None,
+            name: None,
+        };
+        let filename = save(&saved, &dir).unwrap();
+        let loaded = load(&filename);
+        fs::remove_dir_all(&dir).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.deal, Some(deal));
+        assert_eq!(loaded.board, game);
+        assert_eq!(loaded.board.rules(), &Rules::seahaven_towers());
+        assert!(loaded.created.is_some());
+    }
+
+    #[test]
+    fn save_and_load_branches() {
+        let dir = env::temp_dir().join("freecell_test.branches");
+        let deal = Deal::Seed(1234);
+        let mut game = deal.new_game(&Rules::default()).unwrap();
+        let mut undo = BoardUndoStack::new();
+        // the same card put in two different free cells, with an undo in between
+        for cell in 0..2 {
+            game = undo.undo(game);
+            game = undo.update(
+                game.clone(),
+                game.pick_up_card(CardAddress::Column(0)).unwrap(),
+            );
+            game = undo.update(
+                game.clone(),
+                game.place(CardAddress::FreeCell(cell)).unwrap(),
+            );
+        }
+        assert_eq!(undo.list_branches(&game).len(), 1);
+        let saved = SavedGame {
+            deal: Some(deal),
+            board: game.clone(),
+            undo: undo.clone(),
+            created: None,
+            name: None,
+        };
+        let filename = save(&saved, &dir).unwrap();
+        let text = fs::read_to_string(&filename).unwrap();
+        let loaded = load(&filename);
+        fs::remove_dir_all(&dir).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.board, game);
+        assert_eq!(loaded.undo, undo);
+        // the history is saved as position codes, not as the boards in memory
+        assert!(text.contains("\"position\""), "{}", text);
+        assert!(!text.contains("\"foundations\""), "{}", text);
+    }
+
+    #[test]
+    fn reject_mismatched_rules() {
+        let dir = env::temp_dir().join("freecell_test.rules");
+        let deal = Deal::Seed(1234);
+        let saved = SavedGame {
+            deal: Some(deal),
+            board: deal.new_game(&Rules::default()).unwrap(),
+            undo: BoardUndoStack::new(),
+            created: None,
+            name: None,
+        };
+        let filename = save(&saved, &dir).unwrap();
+        let mut save: Value =
+            serde_json::from_str(&fs::read_to_string(&filename).unwrap()).unwrap();
+        save["rules"] = serde_json::to_value(Rules::bakers_game()).unwrap();
+        fs::write(&filename, save.to_string()).unwrap();
+        let loaded = load(&filename);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    // a save written by the first version of the game, with five moves made & one undone
+    const BASELINE_SAVE: &str = include_str!("test_saves/freecell_save.0");
+
+    #[test]
+    fn load_seed_only_save() {
+        let mut filename = env::temp_dir();
+        filename.push("freecell_test.seed_only");
+        fs::write(&filename, BASELINE_SAVE).unwrap();
+        let loaded = load(&filename);
+        fs::remove_file(filename).unwrap();
+        let mut loaded = loaded.unwrap();
+        assert_eq!(loaded.deal, Some(Deal::Seed(1234)));
+        assert_eq!(loaded.board.rules(), &Rules::freecell());
+        assert_eq!(loaded.undo.moves(), 5);
+
+        // the undone move can be redone, and undoing everything gets back to the deal
+        let board = loaded.board.clone();
+        let redone = loaded.undo.redo(board.clone());
+        assert_ne!(redone, board);
+        assert_eq!(loaded.undo.undo(redone), board);
+        let mut board = board;
+        for _ in 0..5 {
+            board = loaded.undo.undo(board);
+        }
+        assert_eq!(board, Board::new_game(1234, &Rules::default()).unwrap());
+    }
+
+    #[test]
+    fn migrate_tuple_save() {
+        let dir = env::temp_dir().join("freecell_test.migrate");
+        let mut filename = env::temp_dir();
+        filename.push("freecell_test.tuple");
+        // saves from before the rules were saved had a deal where the seed used to be
+        let mut old_save: Value = serde_json::from_str(BASELINE_SAVE).unwrap();
+        old_save[0] = serde_json::json!({ "Microsoft": 617 });
+        fs::write(&filename, old_save.to_string()).unwrap();
+        let loaded = load(&filename);
+        fs::remove_file(&filename).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.deal, Some(Deal::Microsoft(617)));
+        assert_eq!(loaded.undo.moves(), 5);
+
+        // saving it again writes the new format, and keeps the history & when it was first made
+        let created = loaded.created;
+        let filename = save(&loaded, &dir).unwrap();
+        let save: Value = serde_json::from_str(&fs::read_to_string(&filename).unwrap()).unwrap();
+        let reloaded = load(&filename);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(save["format"], SAVE_FORMAT);
+        assert_eq!(save["game_version"], env!("CARGO_PKG_VERSION"));
+        let reloaded = reloaded.unwrap();
+        assert_eq!(reloaded.created, created);
+        assert_eq!(reloaded.board, loaded.board);
+        assert_eq!(reloaded.undo, loaded.undo);
+    }
+
+    #[test]
+    fn named_saves() {
+        let dir = env::temp_dir().join("freecell_test.named");
+        let deal = Deal::Seed(1234);
+        let mut game = SavedGame {
+            deal: Some(deal),
+            board: deal.new_game(&Rules::default()).unwrap(),
+            undo: BoardUndoStack::new(),
+            created: None,
+            name: None,
+        };
+        let timestamped = save(&game, &dir).unwrap();
+        game.name = Some("mine".to_string());
+        save(&game, &dir).unwrap();
+        // saving under the same name again replaces the save
+        game.board = game.undo.update(
+            game.board.clone(),
+            game.board.pick_up_card(CardAddress::Column(0)).unwrap(),
+        );
+        game.board = game.undo.update(
+            game.board.clone(),
+            game.board.place(CardAddress::FreeCell(0)).unwrap(),
+        );
+        // as if it had been started from a position code, which has no deal
+        game.deal = None;
+        let named = save(&game, &dir).unwrap();
+        let mut elsewhere = game.clone();
+        elsewhere.name = Some("../mine".to_string());
+        assert!(save(&elsewhere, &dir).is_err());
+
+        let found = find_named_save(&dir, Path::new("mine"));
+        let saves = list_saves(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found.unwrap(), named);
+        let saves = saves.unwrap();
+        let mut names: Vec<&str> = saves.iter().map(|save| save.name.as_str()).collect();
+        names.sort_unstable();
+        let stem = timestamped.file_stem().unwrap().to_str().unwrap();
+        assert_eq!(names, vec![stem, "mine"]);
+        let mine = saves.iter().find(|save| save.name == "mine").unwrap();
+        let loaded = mine.game.as_ref().unwrap();
+        assert_eq!(loaded.name.as_deref(), Some("mine"));
+        assert_eq!(loaded.board, game.board);
+        assert_eq!(loaded.undo.moves(), 1);
+        assert_eq!(loaded.deal, None);
+    }
+
+    #[test]
+    fn format_times() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00");
+        assert_eq!(format_time(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_time(1_000_000_000), "2001-09-09 01:46:40");
+        assert_eq!(format_time(4_107_542_399), "2100-02-28 23:59:59");
+    }
+
+    #[test]
+    fn reject_newer_save() {
+        let mut filename = env::temp_dir();
+        filename.push("freecell_test.newer");
+        let save = serde_json::json!({
+            "format": SAVE_FORMAT + 1,
+            "game_version": "99.0.0",
+            "board": "",
+        });
+        fs::write(&filename, save.to_string()).unwrap();
+        let loaded = load(&filename);
+        fs::remove_file(&filename).unwrap();
+        let error = loaded.err().unwrap().to_string();
+        assert!(error.contains("99.0.0"), "{}", error);
+    }
+
+    #[test]
+    fn reject_corrupted_save() {
+        let mut filename = env::temp_dir();
+        filename.push("freecell_test.corrupted");
+        let save: Value = serde_json::from_str(BASELINE_SAVE).unwrap();
+        // a card copied over another one, and a card that doesn't exist
+        let mut duplicated = save.clone();
+        duplicated[1]["columns"][0][0] = save[1]["columns"][0][1].clone();
+        let mut bad_rank = save;
+        bad_rank[1]["columns"][0][0]["rank"] = 14.into();
+        for corrupted in &[duplicated, bad_rank] {
+            fs::write(&filename, corrupted.to_string()).unwrap();
+            let loaded = load(&filename);
+            fs::remove_file(&filename).unwrap();
+            let error = loaded.unwrap_err();
+            assert!(error.downcast_ref::<BoardError>().is_some(), "{:?}", error);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+
+use super::board::*;
+use super::card::*;
+
+// the number of positions the solver will look at before giving up, unless told otherwise.
+// enough to solve nearly every deal
+pub const DEFAULT_SOLVER_BUDGET: usize = 200_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveOutcome {
+    // the moves which win the game, in order
+    Solved(Vec<Move>),
+    // every reachable position was searched and none of them are won
+    Unsolvable,
+    // the search budget ran out before a solution was found
+    OutOfBudget,
+    // the player is holding cards, so there's no position to search from
+    Holding,
+}
+
+// search for a sequence of moves that wins the game from this position.
+// the budget is the number of distinct positions to look at before giving up
+pub fn solve(board: &Board, budget: usize) -> SolveOutcome {
+    if board.has_floating() {
+        return SolveOutcome::Holding;
+    }
+
+    let (start, forced) = play_safe_moves(board.clone());
+    if is_cleared(start.view()) {
+        return SolveOutcome::Solved(forced);
+    }
+
+    // depth-first search, trying the most promising moves first.
+    // each frame holds the moves that led to it and the positions reachable from it
+    struct Frame {
+        moves: Vec<Move>,
+        children: std::vec::IntoIter<(Vec<Move>, Board)>,
+    }
+    let mut seen = HashSet::new();
+    seen.insert(start.canonical_hash());
+    let mut stack = vec![Frame {
+        moves: forced,
+        children: successors(&start).into_iter(),
+    }];
+    let mut explored = 1;
+
+    while let Some(frame) = stack.last_mut() {
+        if let Some((moves, next)) = frame.children.next() {
+            if is_cleared(next.view()) {
+                let mut solution: Vec<Move> = stack
+                    .iter()
+                    .flat_map(|frame| frame.moves.iter().copied())
+                    .collect();
+                solution.extend(moves);
+                return SolveOutcome::Solved(solution);
+            }
+            if !seen.insert(next.canonical_hash()) {
+                continue;
+            }
+            if explored >= budget {
+                return SolveOutcome::OutOfBudget;
+            }
+            explored += 1;
+            let children = successors(&next).into_iter();
+            stack.push(Frame { moves, children });
+        } else {
+            stack.pop();
+        }
+    }
+    SolveOutcome::Unsolvable
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hint {
+    // the suggested move
+    pub next: Move,
+    // true if the solver found a way to win which starts with this move
+    pub winnable: bool,
+}
+
+// suggest a good next move. uses the first move of a solution if one can be found
+// within the budget, and otherwise the move which looks best right now.
+// returns None if there are no legal moves
+pub fn hint(board: &Board, budget: usize) -> Option<Hint> {
+    match solve(board, budget) {
+        SolveOutcome::Solved(moves) => {
+            if let Some(&next) = moves.first() {
+                return Some(Hint {
+                    next,
+                    winnable: true,
+                });
+            }
+        }
+        SolveOutcome::Holding => return None,
+        SolveOutcome::Unsolvable | SolveOutcome::OutOfBudget => {}
+    }
+    successors(board).first().map(|(moves, _)| Hint {
+        next: moves[0],
+        winnable: false,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeadEnd {
+    // no move changes the position
+    NoMoves,
+    // there are moves left, but none of them lead to a win
+    Lost,
+}
+
+// check whether the game has reached a position it can't be won from.
+// a position counts as lost only if the solver can prove it within the budget,
+// so pass a small budget to keep this quick
+pub fn find_dead_end(board: &Board, budget: usize) -> Option<DeadEnd> {
+    if board.has_floating() || is_cleared(board.view()) {
+        None
+    } else if board.is_stuck() {
+        Some(DeadEnd::NoMoves)
+    } else if solve(board, budget) == SolveOutcome::Unsolvable {
+        Some(DeadEnd::Lost)
+    } else {
+        None
+    }
+}
+
+// true if every card has gone to the foundations.
+// same as BoardView::is_won for a full deck, but also works for positions built by hand
+fn is_cleared(view: &BoardView) -> bool {
+    view.columns.iter().all(|c| c.is_empty()) && view.free_cells.iter().all(|c| c.is_none())
+}
+
+// the legal moves from this position, along with the positions they lead to.
+// moves which are obviously pointless (e.g. moving a whole column into an empty column)
+// or equivalent to another move (e.g. picking a different empty free cell) are left out
+fn candidate_moves(board: &Board) -> Vec<(Move, Board)> {
+    let view = board.view();
+    let first_empty_column = view.columns.iter().position(|c| c.is_empty());
+    let first_empty_free_cell = view.free_cells.iter().position(|c| c.is_none());
+    board
+        .next_positions()
+        .into_iter()
+        .filter(|(mv, _)| match (mv.from, mv.to) {
+            (CardAddress::Column(from), CardAddress::Column(to)) if view.columns[to].is_empty() => {
+                Some(to) == first_empty_column && mv.count < view.columns[from].len()
+            }
+            (_, CardAddress::Column(to)) if view.columns[to].is_empty() => {
+                Some(to) == first_empty_column
+            }
+            (_, CardAddress::FreeCell(to)) => Some(to) == first_empty_free_cell,
+            _ => true,
+        })
+        .collect()
+}
+
+// move every card that can safely go to the foundations there, returning the moves made
+fn play_safe_moves(mut board: Board) -> (Board, Vec<Move>) {
+    let mut moves = Vec::new();
+    'outer: loop {
+        let view = board.view();
+        let sources = view
+            .free_cells
+            .iter()
+            .enumerate()
+            .filter_map(|(n, c)| c.map(|card| (CardAddress::FreeCell(n), card)))
+            .chain(
+                view.columns
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(n, c)| c.last().map(|&card| (CardAddress::Column(n), card))),
+            )
+            .collect::<Vec<(CardAddress, Card)>>();
+        for (from, card) in sources {
+            if board.can_auto_move(card) {
+                let mv = Move {
+                    from,
+                    to: CardAddress::Foundation(card.suit),
+                    count: 1,
+                };
+                board = board.apply(&mv).unwrap();
+                moves.push(mv);
+                continue 'outer;
+            }
+        }
+        return (board, moves);
+    }
+}
+
+// the positions reachable in one move (plus any safe moves that follow), best first
+fn successors(board: &Board) -> Vec<(Vec<Move>, Board)> {
+    let mut scored: Vec<(usize, Vec<Move>, Board)> = candidate_moves(board)
+        .into_iter()
+        .map(|(mv, result)| {
+            let (result, mut forced) = play_safe_moves(result);
+            forced.insert(0, mv);
+            (score(result.view()), forced, result)
+        })
+        .collect();
+    scored.sort_by_key(|(score, _, _)| *score);
+    scored
+        .into_iter()
+        .map(|(_, moves, result)| (moves, result))
+        .collect()
+}
+
+// rough estimate of how far a position is from being won. lower is better
+fn score(view: &BoardView) -> usize {
+    let cards_left: usize = view.columns.iter().map(|c| c.len()).sum::<usize>()
+        + view.free_cells.iter().filter(|c| c.is_some()).count();
+    let occupied_free_cells = view.free_cells.iter().filter(|c| c.is_some()).count();
+    let full_columns = view.columns.iter().filter(|c| !c.is_empty()).count();
+    // how many cards are sitting on top of cards that could go to the foundations next
+    let mut buried = 0;
+    for column in &view.columns {
+        for (depth, card) in column.iter().enumerate() {
+            if view
+                .foundations
+                .iter()
+                .any(|f| f.suit == card.suit && f.rank + 1 == card.rank)
+            {
+                buried += column.len() - depth - 1;
+            }
+        }
+    }
+    cards_left * 4 + buried * 2 + occupied_free_cells + full_columns
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::inspect::*;
+    use super::super::rules::Rules;
+    use super::*;
+
+    fn replay(mut board: Board, moves: &[Move]) -> Board {
+        for &mv in moves {
+            board = board.apply(&mv).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn solves_simple_position() {
+        let game = board_from_columns(vec![
+            vec![
+                Card::new(2, Suit::Spades),
+                Card::new(1, Suit::Hearts),
+                Card::new(2, Suit::Hearts),
+            ],
+            vec![Card::new(1, Suit::Spades)],
+        ]);
+        if let SolveOutcome::Solved(moves) = solve(&game, DEFAULT_SOLVER_BUDGET) {
+            let won = replay(game, &moves);
+            assert!(is_cleared(won.view()));
+            assert_eq!(won.view().foundations[usize::from(Suit::Spades)].rank, 2);
+            assert_eq!(won.view().foundations[usize::from(Suit::Hearts)].rank, 2);
+        } else {
+            panic!("expected a solution");
+        }
+    }
+
+    #[test]
+    fn solves_new_game() {
+        let game = Board::new_game(1, &Rules::default()).unwrap();
+        if let SolveOutcome::Solved(moves) = solve(&game, DEFAULT_SOLVER_BUDGET) {
+            assert!(replay(game, &moves).view().is_won());
+        } else {
+            panic!("expected a solution");
+        }
+    }
+
+    #[test]
+    fn proves_unsolvable() {
+        // the ace is buried under five cards, and there are only four free cells
+        let game = board_from_columns(vec![(1..=6)
+            .map(|n| Card::new(n, Suit::Clubs))
+            .collect::<Vec<Card>>()]);
+        assert_eq!(
+            solve(&game, DEFAULT_SOLVER_BUDGET),
+            SolveOutcome::Unsolvable
+        );
+    }
+
+    #[test]
+    fn hints() {
+        let game = board_from_columns(vec![
+            vec![Card::new(1, Suit::Hearts), Card::new(2, Suit::Spades)],
+            vec![Card::new(2, Suit::Hearts)],
+            vec![Card::new(1, Suit::Spades)],
+        ]);
+        let suggestion = hint(&game, DEFAULT_SOLVER_BUDGET).unwrap();
+        assert!(suggestion.winnable);
+        assert!(game.is_legal(&suggestion.next));
+
+        let stuck = board_from_columns(vec![(1..=6)
+            .map(|n| Card::new(n, Suit::Clubs))
+            .collect::<Vec<Card>>()]);
+        assert!(!hint(&stuck, DEFAULT_SOLVER_BUDGET).unwrap().winnable);
+        assert_eq!(
+            hint(&board_from_columns(Vec::new()), DEFAULT_SOLVER_BUDGET),
+            None
+        );
+    }
+
+    #[test]
+    fn dead_ends() {
+        let buried = board_from_columns(vec![(1..=6)
+            .map(|n| Card::new(n, Suit::Clubs))
+            .collect::<Vec<Card>>()]);
+        assert_eq!(
+            find_dead_end(&buried, DEFAULT_SOLVER_BUDGET),
+            Some(DeadEnd::Lost)
+        );
+
+        let mut stuck = buried;
+        for n in 0..4 {
+            stuck = stuck.pick_up_card(CardAddress::Column(0)).unwrap();
+            stuck = stuck.place(CardAddress::FreeCell(n)).unwrap();
+        }
+        assert_eq!(
+            find_dead_end(&stuck, DEFAULT_SOLVER_BUDGET),
+            Some(DeadEnd::NoMoves)
+        );
+
+        assert_eq!(
+            find_dead_end(&Board::new_game(1, &Rules::default()).unwrap(), 10),
+            None
+        );
+        assert_eq!(find_dead_end(&board_from_columns(Vec::new()), 10), None);
+    }
+
+    #[test]
+    fn refuses_held_cards() {
+        let game = Board::new_game(1, &Rules::default())
+            .unwrap()
+            .pick_up_card(CardAddress::Column(0))
+            .unwrap();
+        assert_eq!(solve(&game, DEFAULT_SOLVER_BUDGET), SolveOutcome::Holding);
+        assert_eq!(hint(&game, DEFAULT_SOLVER_BUDGET), None);
+    }
+
+    #[test]
+    fn respects_budget() {
+        let game = Board::new_game(1, &Rules::default()).unwrap();
+        assert_eq!(solve(&game, 1), SolveOutcome::OutOfBudget);
+    }
+}
@@ -1,59 +1,383 @@
+use std::convert::TryFrom;
 use std::fmt;
+use std::iter;
+use std::mem;
 
 use serde::{Deserialize, Serialize};
 
 use super::board::*;
+use super::card::Move;
+use super::error::{BoardError, ParseError};
+
+// how often the history keeps a whole board instead of a move, so that getting any
+// position back only takes replaying a few moves
+const CHECKPOINT_INTERVAL: usize = 32;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BoardUndoStack {
-    history: Vec<(bool, Board)>,
-    undo_history: Vec<Board>,
+    history: History,
+    undo_history: RedoStack,
+    // the other lines of play, kept instead of being thrown away when a different move is made
+    #[serde(default)]
+    branches: Vec<Branch>,
+}
+
+// a line of play other than the current one. `position` is the branch point, where the
+// line parts ways with the one that replaced it, or wherever the player was when they
+// switched away from it. the rest of the line is in `undo_history`, waiting to be redone
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Branch {
+    history: History,
+    position: Board,
+    undo_history: RedoStack,
+}
+
+impl Branch {
+    // every position in the branch, from the start of the game
+    fn positions(&self) -> Vec<Board> {
+        self.history
+            .boards()
+            .into_iter()
+            .map(|(_, board)| board)
+            .chain(iter::once(self.position.clone()))
+            .chain(self.undo_history.boards())
+            .collect()
+    }
+}
+
+// where another branch parts ways with the current one, counted in moves from the start
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BranchSummary {
+    pub parts_at: usize,
+    // how many moves the branch goes on for after that
+    pub length: usize,
+}
+
+// one position in the history, stored as the move that leads to it from the position
+// before it. positions that don't follow from one move are stored whole.
+// untagged, so that saves from before moves were stored load as whole boards
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Moved(Move),
+    Checkpoint(Box<Board>),
+}
+
+impl Entry {
+    fn between(previous: &Board, board: &Board) -> Self {
+        match previous.move_to(board) {
+            Some(mv) => Entry::Moved(mv),
+            None => Entry::Checkpoint(Box::new(board.clone())),
+        }
+    }
+
+    // the position this entry stands for, given the position before it
+    fn replay(&self, previous: Option<&Board>) -> Result<Board, BoardError> {
+        match (self, previous) {
+            (Entry::Checkpoint(board), _) => Ok(*board.clone()),
+            (Entry::Moved(mv), Some(previous)) => {
+                previous.apply(mv).map_err(|_| BoardError::BadHistory)
+            }
+            (Entry::Moved(_), None) => Err(BoardError::BadHistory),
+        }
+    }
+
+    // check the entry's position, given the position before it, and return it
+    fn validate(&self, previous: Option<&Board>) -> Result<Board, BoardError> {
+        if let Entry::Checkpoint(board) = self {
+            board.validate()?;
+        }
+        self.replay(previous)
+    }
+}
+
+// the positions before the current one, oldest first, and whether each was a sneak update.
+// each is stored relative to the one before it, with a checkpoint every so often.
+// the hash of every position is kept too, so that looking for a position only has to
+// replay the ones that might match. hashes aren't saved, and are worked out on loading
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<(bool, Entry)>", into = "Vec<(bool, Entry)>")]
+struct History {
+    entries: Vec<(bool, Entry)>,
+    hashes: Vec<u64>,
+}
+
+impl TryFrom<Vec<(bool, Entry)>> for History {
+    type Error = BoardError;
+    fn try_from(entries: Vec<(bool, Entry)>) -> Result<Self, Self::Error> {
+        let mut hashes = Vec::with_capacity(entries.len());
+        let mut previous: Option<Board> = None;
+        for (_, entry) in &entries {
+            let board = entry.replay(previous.as_ref())?;
+            hashes.push(board.position_hash());
+            previous = Some(board);
+        }
+        Ok(History { entries, hashes })
+    }
+}
+
+impl From<History> for Vec<(bool, Entry)> {
+    fn from(history: History) -> Self {
+        history.entries
+    }
+}
+
+impl History {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // the position at an index, replayed from the last checkpoint at or before it
+    fn get(&self, index: usize) -> Board {
+        let start = self.entries[..=index]
+            .iter()
+            .rposition(|(_, entry)| matches!(entry, Entry::Checkpoint(_)))
+            .unwrap();
+        let mut board: Option<Board> = None;
+        for (_, entry) in &self.entries[start..=index] {
+            board = Some(entry.replay(board.as_ref()).unwrap());
+        }
+        board.unwrap()
+    }
+
+    // true if the position at an index is this board, only replaying it if the hashes match
+    fn is_at(&self, index: usize, board: &Board) -> bool {
+        self.hashes[index] == board.position_hash() && &self.get(index) == board
+    }
+
+    fn last(&self) -> Option<(bool, Board)> {
+        let (sneak, _) = self.entries.last()?;
+        Some((*sneak, self.get(self.len() - 1)))
+    }
+
+    fn push(&mut self, sneak: bool, board: Board) {
+        self.hashes.push(board.position_hash());
+        let entry = if self.len() % CHECKPOINT_INTERVAL == 0 {
+            Entry::Checkpoint(Box::new(board))
+        } else {
+            Entry::between(&self.get(self.len() - 1), &board)
+        };
+        self.entries.push((sneak, entry));
+    }
+
+    fn pop(&mut self) -> Option<(bool, Board)> {
+        let last = self.last()?;
+        self.entries.pop();
+        self.hashes.pop();
+        Some(last)
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+        self.hashes.truncate(len);
+    }
+
+    // remove the positions from an index onwards, and return them oldest first
+    fn split_off(&mut self, index: usize) -> Vec<Board> {
+        let boards = (index..self.len()).map(|n| self.get(n)).collect();
+        self.truncate(index);
+        boards
+    }
+
+    // every position, oldest first
+    fn boards(&self) -> Vec<(bool, Board)> {
+        let mut boards: Vec<(bool, Board)> = Vec::new();
+        for (sneak, entry) in &self.entries {
+            let previous = boards.last().map(|(_, board)| board);
+            boards.push((*sneak, entry.replay(previous).unwrap()));
+        }
+        boards
+    }
+
+    fn validate(&self) -> Result<(), BoardError> {
+        let mut previous: Option<Board> = None;
+        for (_, entry) in &self.entries {
+            previous = Some(entry.validate(previous.as_ref())?);
+        }
+        Ok(())
+    }
+}
+
+// the undone positions, with the next one to be redone last. that one is stored whole,
+// and each of the others relative to the one after it in the stack
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct RedoStack(Vec<Entry>);
+
+impl RedoStack {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn last(&self) -> Option<&Board> {
+        match self.0.last() {
+            Some(Entry::Checkpoint(board)) => Some(board),
+            _ => None,
+        }
+    }
+
+    fn push(&mut self, board: Board) {
+        if let Some(top) = self.0.last_mut() {
+            if let Entry::Checkpoint(next) = top {
+                *top = Entry::between(&board, next);
+            }
+        }
+        self.0.push(Entry::Checkpoint(Box::new(board)));
+    }
+
+    fn pop(&mut self) -> Option<Board> {
+        let board = match self.0.pop()? {
+            Entry::Checkpoint(board) => *board,
+            Entry::Moved(_) => unreachable!(),
+        };
+        if let Some(top) = self.0.last_mut() {
+            *top = Entry::Checkpoint(Box::new(top.replay(Some(&board)).unwrap()));
+        }
+        Some(board)
+    }
+
+    // every position, in the order they'd be redone
+    fn boards(&self) -> Vec<Board> {
+        let mut boards: Vec<Board> = Vec::new();
+        for entry in self.0.iter().rev() {
+            boards.push(entry.replay(boards.last()).unwrap());
+        }
+        boards
+    }
+
+    fn validate(&self) -> Result<(), BoardError> {
+        let mut previous: Option<Board> = None;
+        for entry in self.0.iter().rev() {
+            previous = Some(entry.validate(previous.as_ref())?);
+        }
+        Ok(())
+    }
+}
+
+// the undo history as it's written in save files. positions are written as position codes
+// and steps as moves, so that saves don't depend on how boards or the history are kept
+// in memory
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UndoRecord {
+    history: Vec<StepRecord>,
+    // the undone positions, with the next one to be redone last
+    redo: Vec<Step>,
+    branches: Vec<BranchRecord>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StepRecord {
+    sneak: bool,
+    #[serde(flatten)]
+    step: Step,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Step {
+    Move(Move),
+    Position(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct BranchRecord {
+    history: Vec<StepRecord>,
+    position: String,
+    redo: Vec<Step>,
+}
+
+impl Step {
+    fn of(entry: &Entry) -> Self {
+        match entry {
+            Entry::Moved(mv) => Step::Move(*mv),
+            Entry::Checkpoint(board) => Step::Position(board.to_code()),
+        }
+    }
+
+    fn entry(self) -> Result<Entry, ParseError> {
+        Ok(match self {
+            Step::Move(mv) => Entry::Moved(mv),
+            Step::Position(code) => Entry::Checkpoint(Box::new(Board::from_code(&code)?)),
+        })
+    }
+}
+
+fn history_record(history: &History) -> Vec<StepRecord> {
+    history
+        .entries
+        .iter()
+        .map(|(sneak, entry)| StepRecord {
+            sneak: *sneak,
+            step: Step::of(entry),
+        })
+        .collect()
+}
+
+fn history_from_record(record: Vec<StepRecord>) -> Result<History, ParseError> {
+    let entries = record
+        .into_iter()
+        .map(|step| Ok((step.sneak, step.step.entry()?)))
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    Ok(History::try_from(entries)?)
+}
+
+fn redo_from_record(record: Vec<Step>) -> Result<RedoStack, ParseError> {
+    let entries = record
+        .into_iter()
+        .map(Step::entry)
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    // the next position to be redone is always kept whole
+    match entries.last() {
+        Some(Entry::Moved(_)) => Err(BoardError::BadHistory.into()),
+        _ => Ok(RedoStack(entries)),
+    }
+}
+
+// true if two boards are the same position, comparing their hashes before the whole boards
+fn same_position(a: &Board, b: &Board) -> bool {
+    a.position_hash() == b.position_hash() && a == b
 }
 
 impl BoardUndoStack {
     pub fn new() -> Self {
         BoardUndoStack {
-            history: Vec::new(),
-            undo_history: Vec::new(),
+            history: History::default(),
+            undo_history: RedoStack::default(),
+            branches: Vec::new(),
         }
     }
 
     pub fn update(&mut self, old_state: Board, new_state: Board) -> Board {
         // no no-ops
-        if old_state == new_state {
+        if same_position(&old_state, &new_state) {
             return new_state;
         }
 
         // if we're manually undoing a move, don't destroy the redo stack.
         // just truncate the undo stack back to the point we're undoing it to
         let mut new_len = None;
-        for (n, (_, prev_state)) in self.history.iter().enumerate().rev().take(2) {
-            if prev_state == &new_state {
+        let len = self.history.len();
+        for n in (len.saturating_sub(2)..len).rev() {
+            if self.history.is_at(n, &new_state) {
                 new_len = Some(n);
             }
         }
         if let Some(n) = new_len {
-            let mut truncated: Vec<Board> = self
-                .history
-                .split_off(n)
-                .into_iter()
-                .map(|(_, state)| state)
-                .rev()
-                .collect();
-            self.undo_history.append(&mut truncated);
+            for state in self.history.split_off(n).into_iter().rev() {
+                self.undo_history.push(state);
+            }
             return self.undo_history.pop().unwrap();
         }
 
         if !old_state.has_floating() {
-            self.history.push((false, old_state));
+            self.history.push(false, old_state);
         }
 
         // if we're manually re-doing a move, pop it off the redo stack.
         if let Some(undone_state) = self.undo_history.last() {
-            if &new_state == undone_state {
+            if same_position(&new_state, undone_state) {
                 self.undo_history.pop();
             } else if !new_state.has_floating() {
-                self.undo_history = Vec::new();
+                self.branch_off();
             }
         }
 
@@ -62,19 +386,16 @@ impl BoardUndoStack {
 
     // sneak updates will, upon being undone, immediately trigger another undo
     pub fn sneak_update(&mut self, old_state: Board, new_state: Board) -> Board {
-        if let Some((_, last_state)) = self.history.last() {
-            // don't push no-ops
-            if last_state != &old_state {
-                self.history.push((true, old_state));
-            }
-        } else {
-            self.history.push((true, old_state));
+        // don't push no-ops
+        let len = self.history.len();
+        if len == 0 || !self.history.is_at(len - 1, &old_state) {
+            self.history.push(true, old_state);
         }
         if let Some(undone_state) = self.undo_history.last() {
-            if &new_state == undone_state {
+            if same_position(&new_state, undone_state) {
                 self.undo_history.pop();
             } else {
-                self.undo_history = Vec::new();
+                self.branch_off();
             }
         }
         new_state
@@ -99,28 +420,163 @@ impl BoardUndoStack {
     pub fn redo(&mut self, state: Board) -> Board {
         if let Some(undone_state) = self.undo_history.pop() {
             if !state.has_floating() {
-                self.history.push((false, state));
+                self.history.push(false, state);
             }
             undone_state
         } else {
             state
         }
     }
+
+    // a different move was made after undoing, so the undone moves become a branch of
+    // their own, left at the position where they part ways with the new move
+    fn branch_off(&mut self) {
+        if self.undo_history.is_empty() {
+            return;
+        }
+        let undo_history = mem::take(&mut self.undo_history);
+        if let Some((_, position)) = self.history.last() {
+            let mut history = self.history.clone();
+            history.truncate(self.history.len() - 1);
+            self.branches.push(Branch {
+                history,
+                position,
+                undo_history,
+            });
+        }
+    }
+
+    // switch to another branch, at the position it was left at.
+    // the current line of play becomes a branch, so it can be switched back to
+    pub fn switch_branch(&mut self, state: Board, index: usize) -> Board {
+        if state.has_floating() || index >= self.branches.len() {
+            return state;
+        }
+        let branch = self.branches.remove(index);
+        self.branches.push(Branch {
+            history: mem::replace(&mut self.history, branch.history),
+            position: state,
+            undo_history: mem::replace(&mut self.undo_history, branch.undo_history),
+        });
+        branch.position
+    }
+
+    // undo back to the last position where another branch parts ways with this one
+    pub fn jump_to_branch_point(&mut self, mut state: Board) -> Board {
+        if state.has_floating() {
+            return state;
+        }
+        let target = self
+            .list_branches(&state)
+            .iter()
+            .map(|branch| branch.parts_at)
+            .filter(|&parts_at| parts_at < self.history.len())
+            .max();
+        if let Some(target) = target {
+            while self.history.len() > target {
+                state = self.undo(state);
+            }
+        }
+        state
+    }
+
+    // the other branches, in the order they're switched to
+    pub fn list_branches(&self, state: &Board) -> Vec<BranchSummary> {
+        let line: Vec<Board> = self
+            .history
+            .boards()
+            .into_iter()
+            .map(|(_, board)| board)
+            .chain(iter::once(state.clone()))
+            .chain(self.undo_history.boards())
+            .collect();
+        self.branches
+            .iter()
+            .map(|branch| {
+                let positions = branch.positions();
+                let shared = line
+                    .iter()
+                    .zip(&positions)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                BranchSummary {
+                    parts_at: shared.saturating_sub(1),
+                    length: positions.len() - shared,
+                }
+            })
+            .collect()
+    }
+
+    // how many moves have been made to get to the current position, not counting auto-moves
+    pub fn moves(&self) -> usize {
+        self.history
+            .entries
+            .iter()
+            .filter(|(sneak, _)| !sneak)
+            .count()
+    }
+
+    // the history as it's written in save files
+    pub fn to_record(&self) -> UndoRecord {
+        UndoRecord {
+            history: history_record(&self.history),
+            redo: self.undo_history.0.iter().map(Step::of).collect(),
+            branches: self
+                .branches
+                .iter()
+                .map(|branch| BranchRecord {
+                    history: history_record(&branch.history),
+                    position: branch.position.to_code(),
+                    redo: branch.undo_history.0.iter().map(Step::of).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    // read the history back from a save file. the moves in it still need validating
+    pub fn from_record(record: UndoRecord) -> Result<Self, ParseError> {
+        let mut branches = Vec::new();
+        for branch in record.branches {
+            branches.push(Branch {
+                history: history_from_record(branch.history)?,
+                position: Board::from_code(&branch.position)?,
+                undo_history: redo_from_record(branch.redo)?,
+            });
+        }
+        Ok(BoardUndoStack {
+            history: history_from_record(record.history)?,
+            undo_history: redo_from_record(record.redo)?,
+            branches,
+        })
+    }
+
+    // check every board in the history, in case it came from somewhere untrustworthy
+    pub fn validate(&self) -> Result<(), BoardError> {
+        self.history.validate()?;
+        self.undo_history.validate()?;
+        for branch in &self.branches {
+            branch.history.validate()?;
+            branch.position.validate()?;
+            branch.undo_history.validate()?;
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for BoardUndoStack {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "UNDO:")?;
-        for (sneak, state) in self.history.iter() {
-            if *sneak {
+        for (sneak, state) in self.history.boards() {
+            if sneak {
                 write!(f, "\n  sneak:")?;
             }
             write!(f, "\n{}", state.view())?;
         }
         write!(f, "\nREDO:")?;
-        for state in self.undo_history.iter() {
+        for state in self.undo_history.boards().iter().rev() {
             write!(f, "\n{}", state.view())?;
         }
+        write!(f, "\nBRANCHES: {}", self.branches.len())?;
         Ok(())
     }
 }
@@ -129,6 +585,8 @@ impl fmt::Display for BoardUndoStack {
 mod test {
     use super::super::board::inspect::*;
     use super::super::card::*;
+    use super::super::rules::Rules;
+    use super::super::solver::*;
     use super::*;
 
     #[test]
@@ -280,7 +738,10 @@ mod test {
             game.pick_up_card(CardAddress::Column(0)).unwrap(),
         );
         game = undo_stack.update(game.clone(), game.place(CardAddress::Column(1)).unwrap());
-        game = undo_stack.sneak_update(game.clone(), game.auto_move_to_foundations().unwrap());
+        game = undo_stack.sneak_update(
+            game.clone(),
+            game.auto_move_to_foundations(AutoMovePolicy::Safe).unwrap(),
+        );
 
         // sneak skipping during undo
         game = undo_stack.undo(game);
@@ -337,4 +798,154 @@ mod test {
         game = undo_stack.redo(game);
         assert_eq!(game, game_state_3);
     }
+
+    #[test]
+    fn branches() {
+        let mut game = board_from_columns(vec![
+            vec![Card::new(2, Suit::Clubs), Card::new(1, Suit::Diamonds)],
+            Vec::new(),
+            Vec::new(),
+        ]);
+        let mut undo_stack = BoardUndoStack::new();
+
+        let game_state_1 = game.clone();
+        game = undo_stack.update(
+            game.clone(),
+            game.pick_up_card(CardAddress::Column(0)).unwrap(),
+        );
+        game = undo_stack.update(game.clone(), game.place(CardAddress::Column(1)).unwrap());
+        let game_state_2 = game.clone();
+
+        // a different move after undoing keeps the old one as a branch
+        game = undo_stack.undo(game);
+        game = undo_stack.update(
+            game.clone(),
+            game.pick_up_card(CardAddress::Column(0)).unwrap(),
+        );
+        game = undo_stack.update(game.clone(), game.place(CardAddress::Column(2)).unwrap());
+        let game_state_3 = game.clone();
+        assert_eq!(
+            undo_stack.list_branches(&game),
+            vec![BranchSummary {
+                parts_at: 0,
+                length: 1
+            }]
+        );
+        assert_eq!(undo_stack.redo(game.clone()), game_state_3);
+
+        // the old branch starts where it parted ways
+        game = undo_stack.switch_branch(game, 0);
+        assert_eq!(game, game_state_1);
+        game = undo_stack.redo(game);
+        assert_eq!(game, game_state_2);
+
+        // and the new branch is where it was left
+        game = undo_stack.switch_branch(game, 0);
+        assert_eq!(game, game_state_3);
+        game = undo_stack.jump_to_branch_point(game);
+        assert_eq!(game, game_state_1);
+        game = undo_stack.redo(game);
+        assert_eq!(game, game_state_3);
+        assert_eq!(undo_stack.list_branches(&game).len(), 1);
+    }
+
+    #[test]
+    fn stores_moves() {
+        let mut game = board_from_columns(vec![
+            vec![Card::new(2, Suit::Clubs)],
+            Vec::new(),
+            Vec::new(),
+        ]);
+        let mut undo_stack = BoardUndoStack::new();
+        let mut positions = vec![game.clone()];
+
+        // enough moves to need a few checkpoints. the card goes round three columns,
+        // since moving it straight back would count as undoing
+        for n in 0..3 * CHECKPOINT_INTERVAL {
+            game = undo_stack.update(
+                game.clone(),
+                game.pick_up_card(CardAddress::Column(n % 3)).unwrap(),
+            );
+            game = undo_stack.update(
+                game.clone(),
+                game.place(CardAddress::Column((n + 1) % 3)).unwrap(),
+            );
+            positions.push(game.clone());
+        }
+        let checkpoints = undo_stack
+            .history
+            .entries
+            .iter()
+            .filter(|(_, entry)| matches!(entry, Entry::Checkpoint(_)))
+            .count();
+        assert_eq!(checkpoints, 3);
+
+        // every position comes back in order, and forward again
+        for position in positions.iter().rev().skip(1) {
+            game = undo_stack.undo(game);
+            assert_eq!(&game, position);
+        }
+        for position in positions.iter().skip(1) {
+            game = undo_stack.redo(game);
+            assert_eq!(&game, position);
+        }
+
+        // the hashes aren't saved, and are worked out again on loading
+        let saved = serde_json::to_value(&undo_stack).unwrap();
+        assert!(!saved.to_string().contains("hashes"));
+        let loaded: BoardUndoStack = serde_json::from_value(saved).unwrap();
+        assert_eq!(loaded, undo_stack);
+    }
+
+    #[test]
+    fn records() {
+        let mut game = Board::new_game(5, &Rules::default()).unwrap();
+        let mut undo_stack = BoardUndoStack::new();
+        // some moves from a solution, enough for a checkpoint, then an undo and a different
+        // move for a branch
+        let moves = match solve(&game, DEFAULT_SOLVER_BUDGET) {
+            SolveOutcome::Solved(moves) => moves,
+            outcome => panic!("{:?}", outcome),
+        };
+        let mut positions = vec![game.clone()];
+        for mv in &moves[..CHECKPOINT_INTERVAL + 4] {
+            game = undo_stack.update(game.clone(), game.apply(mv).unwrap());
+            positions.push(game.clone());
+        }
+        for _ in 0..2 {
+            game = undo_stack.undo(game);
+        }
+        let (mv, next) = game
+            .next_positions()
+            .into_iter()
+            .find(|(_, next)| !positions.contains(next))
+            .unwrap();
+        game = undo_stack.update(game.clone(), next);
+        assert_eq!(undo_stack.list_branches(&game).len(), 1);
+
+        let record = undo_stack.to_record();
+        assert_eq!(BoardUndoStack::from_record(record.clone()), Ok(undo_stack));
+        // a move can't be the next thing to redo, since it has nothing to be made from
+        let mut bad = record;
+        bad.redo.push(Step::Move(mv));
+        assert!(BoardUndoStack::from_record(bad).is_err());
+    }
+
+    #[test]
+    fn loads_snapshots() {
+        // undo stacks used to hold every position whole
+        let game = board_from_columns(vec![vec![Card::new(2, Suit::Clubs)], Vec::new()]);
+        let moved = game
+            .pick_up_card(CardAddress::Column(0))
+            .unwrap()
+            .place(CardAddress::Column(1))
+            .unwrap();
+        let old = serde_json::json!({
+            "history": [[false, game]],
+            "undo_history": [moved],
+        });
+        let mut undo_stack: BoardUndoStack = serde_json::from_value(old).unwrap();
+        assert_eq!(undo_stack.redo(game.clone()), moved);
+        assert_eq!(undo_stack.undo(moved), game);
+    }
 }
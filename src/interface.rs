@@ -2,12 +2,22 @@ use anyhow::Result;
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use std::convert::TryInto;
-use std::env;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::SystemTime;
 
 use super::display::*;
 use super::gamelogic::*;
 use super::*;
 
+// how many positions to search when looking for a hint.
+// the search runs in the background, but is kept small so that the hint shows up quickly
+const HINT_SOLVER_BUDGET: usize = 5_000;
+
+// how many positions to search when checking whether the game is lost after every move.
+// tiny, so that only hopeless endgames get caught
+pub const DEAD_END_SOLVER_BUDGET: usize = 100;
+
 // Holds a few state machines and times that regulate the UI
 pub struct InterfaceState {
     // text to display in corner & the time when it'll disappear
@@ -16,8 +26,20 @@ pub struct InterfaceState {
     pub n_key_state: NewGameState,
     // how long until the game can automatically move a card to the foundation
     pub next_auto_move: Instant,
+    // which cards get moved to the foundations automatically
+    pub auto_move_policy: AutoMovePolicy,
+    // set by a one-off request to move every card that can go to the foundations
+    pub moving_all_home: bool,
+    // whether the player has been told that the game can be finished automatically
+    pub finish_offered: bool,
     // is the s key being held
     pub s_key_held: bool,
+    // the last hint shown & the board it was for. only drawn while the board is unchanged
+    pub hint: Option<(Board, Move)>,
+    // a hint being worked out in the background & the hash of the position it's for
+    pub hint_search: Option<(u64, Receiver<Option<Hint>>)>,
+    // whether the game can't be won anymore, & the hash of the position that was checked
+    pub dead_end: Option<(u64, Option<DeadEnd>)>,
 }
 
 // NewGameState is a ype defining a finite state machine which
@@ -30,7 +52,7 @@ pub enum NewGameState {
 }
 
 impl InterfaceState {
-    pub fn new(ui_settings: &UiSettings) -> Self {
+    pub fn new(ui_settings: &UiSettings, auto_move_policy: AutoMovePolicy) -> Self {
         // timeout until an automatic move can be performed
         let next_auto_move = Instant::now() + ui_settings.timings().auto_move_secs;
         // status text to draw to screen & time when it will fade
@@ -38,12 +60,23 @@ impl InterfaceState {
         // status of "hold n to quit the game" system
         let n_key_state = NewGameState::Ready;
         let s_key_held = false;
+        let hint = None;
+        let hint_search = None;
+        let dead_end = None;
+        let moving_all_home = false;
+        let finish_offered = false;
 
         InterfaceState {
             next_auto_move,
+            auto_move_policy,
+            moving_all_home,
+            finish_offered,
             status_text,
             n_key_state,
             s_key_held,
+            hint,
+            hint_search,
+            dead_end,
         }
     }
 }
@@ -58,25 +91,22 @@ fn pick_up_cards(state: &mut GameState, x: i32, y: i32) {
             .rev()
         {
             if rect_intersect(x, y, &card_rect.rect) {
-                if let Some(size) = card_rect.stack_size {
+                let result = if let Some(size) = card_rect.stack_size {
                     // if the card being clicked on is part of a stack
                     // pick up the card and all the cards stacked on top of it
-                    match state.board.pick_up_stack(card_rect.address, size) {
-                        Ok(new_state) => {
-                            state.board = state.undo_stack.update(state.board.clone(), new_state);
-                        }
-                        Err(MoveError::CannotPickUp { .. }) => {}
-                        Err(_) => unreachable!(),
-                    }
+                    state.board.pick_up_stack(card_rect.address, size)
                 } else {
                     // if the card is not in a stack, just pick up the one card
-                    match state.board.pick_up_card(card_rect.address) {
-                        Ok(new_state) => {
-                            state.board = state.undo_stack.update(state.board.clone(), new_state);
-                        }
-                        Err(MoveError::CannotPickUp { .. }) => {}
-                        Err(_) => unreachable!(),
+                    state.board.pick_up_card(card_rect.address)
+                };
+                match result {
+                    Ok(new_state) => {
+                        state.board = state.undo_stack.update(state.board.clone(), new_state);
+                    }
+                    Err(MoveError::CannotPickUp { reason, .. }) => {
+                        explain_rejection(state, &reason);
                     }
+                    Err(_) => unreachable!(),
                 }
             }
         }
@@ -87,9 +117,11 @@ fn pick_up_cards(state: &mut GameState, x: i32, y: i32) {
 fn place_cards(state: &mut GameState, x: i32, y: i32) {
     // if the player is holding cards
     if state.board.has_floating() {
+        let held = state.board.view().floating.clone().unwrap_or_default();
         let mut did_something = false;
+        let mut rejection = None;
         // find the location in the game layout corresponding to the mouse's location
-        for (address, rect) in get_placement_zones(&state.ui_settings).iter() {
+        for (address, rect) in get_placement_zones(state.board.view(), &state.ui_settings).iter() {
             if rect_intersect(x, y, rect) {
                 // place the card at that location
                 match state.board.place(*address) {
@@ -97,20 +129,65 @@ fn place_cards(state: &mut GameState, x: i32, y: i32) {
                         did_something = true;
                         state.board = state.undo_stack.update(state.board.clone(), new_state);
                     }
-                    Err(MoveError::CannotPlace { .. }) => {}
+                    Err(MoveError::CannotPlace { reason, .. }) => {
+                        rejection = Some((*address, reason));
+                    }
                     Err(_) => unreachable!(),
                 }
             }
         }
         if !did_something {
             state.board = state.undo_stack.undo(state.board.clone());
+            if let Some((address, reason)) = rejection {
+                // dropping the cards back where they came from isn't a mistake
+                if !holds_cards_at(&state.board, address, &held) {
+                    explain_rejection(state, &reason);
+                }
+            }
+        }
+    }
+}
+
+// true if these cards are on top at this address
+fn holds_cards_at(board: &Board, address: CardAddress, cards: &[Card]) -> bool {
+    let view = board.view();
+    match address {
+        CardAddress::Column(n) => view.columns.get(n).is_some_and(|c| c.ends_with(cards)),
+        CardAddress::FreeCell(n) => {
+            cards.len() == 1 && view.free_cells.get(n) == Some(&Some(cards[0]))
         }
+        CardAddress::Foundation(_) => false,
     }
 }
 
-fn copy_seed_to_clipboard(state: &mut GameState) {
+// tell the player why their cards couldn't go where they tried to put them.
+// rejections that only come from clicking in odd places are left unexplained
+fn explain_rejection(state: &mut GameState, reason: &MoveRejection) {
+    match reason {
+        MoveRejection::StackTooLarge { .. }
+        | MoveRejection::DoesNotFit { .. }
+        | MoveRejection::UnsoundStack { .. }
+        | MoveRejection::KingsOnly { .. }
+        | MoveRejection::CellOccupied
+        | MoveRejection::SingleCardsOnly => {}
+        _ => return,
+    }
+    // the reasons are written to go in the middle of a sentence, so start them with a capital
+    let reason = reason.to_string();
+    let mut chars = reason.chars();
+    let text = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => reason,
+    };
+    state.interface_state.status_text = Some((
+        Instant::now() + state.ui_settings.timings().status_display_secs,
+        text,
+    ));
+}
+
+fn copy_to_clipboard(state: &mut GameState, text: String) {
     if let Some(ctx) = &mut state.clipboard {
-        if let Err(e) = ctx.set_contents(state.seed.to_string()) {
+        if let Err(e) = ctx.set_contents(text) {
             state.interface_state.status_text = Some((
                 Instant::now() + state.ui_settings.timings().status_display_secs,
                 "Clipboard Error".to_string(),
@@ -135,15 +212,183 @@ fn copy_seed_to_clipboard(state: &mut GameState) {
     }
 }
 
+// play the moves written on the clipboard in standard notation.
+// either all of the moves are played, or none of them are
+fn paste_moves(state: &mut GameState) {
+    if state.board.has_floating() {
+        return;
+    }
+    let text = match state.clipboard.as_mut().map(|ctx| ctx.get_contents()) {
+        Some(Ok(text)) => text,
+        Some(Err(e)) => {
+            if !state.opt.quiet {
+                eprintln!("Couldn't access clipboard {}", e);
+            }
+            state.interface_state.status_text = Some((
+                Instant::now() + state.ui_settings.timings().status_display_secs,
+                "Clipboard Error".to_string(),
+            ));
+            return;
+        }
+        None => {
+            if !state.opt.quiet {
+                eprintln!("Clipboard is unavailable");
+            }
+            state.interface_state.status_text = Some((
+                Instant::now() + state.ui_settings.timings().status_display_secs,
+                "Clipboard Error".to_string(),
+            ));
+            return;
+        }
+    };
+    let status = match parse_moves(&state.board, &text) {
+        Ok(moves) => {
+            for mv in moves.iter() {
+                let new_state = state.board.apply(mv).unwrap();
+                state.board = state.undo_stack.update(state.board.clone(), new_state);
+            }
+            format!("Played {} moves", moves.len())
+        }
+        Err(e) => {
+            if !state.opt.quiet {
+                eprintln!("Couldn't play moves: {}", e);
+            }
+            "Couldn't play moves".to_string()
+        }
+    };
+    state.interface_state.status_text = Some((
+        Instant::now() + state.ui_settings.timings().status_display_secs,
+        status,
+    ));
+}
+
+// start sending every card that can go to the foundations there, one at a time.
+// the first card is a move of its own, so that a single undo takes back the whole sweep
+fn move_all_home(state: &mut GameState) {
+    if let Some(new_state) = state
+        .board
+        .auto_move_to_foundations(AutoMovePolicy::Aggressive)
+    {
+        state.board = state.undo_stack.update(state.board.clone(), new_state);
+        state.interface_state.moving_all_home = true;
+        state.interface_state.next_auto_move =
+            Instant::now() + state.ui_settings.timings().auto_complete_secs;
+    }
+}
+
+// switch to the next other line of play, if there is one
+fn switch_branch(state: &mut GameState) {
+    if state.board.has_floating() {
+        return;
+    }
+    let count = state.undo_stack.list_branches(&state.board).len();
+    let text = if count == 0 {
+        "No other branches".to_string()
+    } else {
+        state.interface_state.moving_all_home = false;
+        state.board = state.undo_stack.switch_branch(state.board.clone(), 0);
+        format!("Switched branch ({} others)", count)
+    };
+    state.interface_state.status_text = Some((
+        Instant::now() + state.ui_settings.timings().status_display_secs,
+        text,
+    ));
+}
+
+// show where the other lines of play part ways with this one
+fn list_branches(state: &mut GameState) {
+    let branches = state.undo_stack.list_branches(&state.board);
+    let text = if branches.is_empty() {
+        "No other branches".to_string()
+    } else {
+        let list: Vec<String> = branches
+            .iter()
+            .enumerate()
+            .map(|(n, branch)| {
+                format!(
+                    "{}) {} moves from move {}",
+                    n + 1,
+                    branch.length,
+                    branch.parts_at
+                )
+            })
+            .collect();
+        format!("Branches: {}", list.join(", "))
+    };
+    state.interface_state.status_text = Some((
+        Instant::now() + state.ui_settings.timings().status_display_secs,
+        text,
+    ));
+}
+
+// start looking for a good next move, without holding up the game while the solver runs
+fn show_hint(state: &mut GameState) {
+    if state.board.has_floating() {
+        return;
+    }
+    let board = state.board.clone();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // the game may have stopped waiting for this hint, in which case nobody hears it
+        let _ = sender.send(hint(&board, HINT_SOLVER_BUDGET));
+    });
+    state.interface_state.hint_search = Some((state.board.position_hash(), receiver));
+}
+
+// show the hint once the search has finished, as long as the board hasn't changed since
+pub fn receive_hint(state: &mut GameState) {
+    let result = match &state.interface_state.hint_search {
+        Some((hash, _)) if *hash != state.board.position_hash() => {
+            state.interface_state.hint_search = None;
+            return;
+        }
+        Some((_, receiver)) => match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                state.interface_state.hint_search = None;
+                return;
+            }
+        },
+        None => return,
+    };
+    state.interface_state.hint_search = None;
+    let text = match result {
+        Some(hint) => {
+            state.interface_state.hint = Some((state.board.clone(), hint.next));
+            if hint.winnable {
+                format!("Hint: {}", hint.next)
+            } else {
+                format!("Hint: {} (no win found)", hint.next)
+            }
+        }
+        None => {
+            state.interface_state.hint = None;
+            "No moves left".to_string()
+        }
+    };
+    state.interface_state.status_text = Some((
+        Instant::now() + state.ui_settings.timings().status_display_secs,
+        text,
+    ));
+}
+
 fn save_game(state: &mut GameState) -> Result<()> {
-    match save(
-        state.seed,
-        &state.board,
-        &state.undo_stack,
-        env::current_dir()?,
-        "freecell_save.",
-    ) {
+    // later saves of the same game keep the time it was first saved
+    let created = *state
+        .created
+        .get_or_insert_with(|| unix_time(SystemTime::now()));
+    let game = SavedGame {
+        deal: state.deal,
+        board: state.board.clone(),
+        undo: state.undo_stack.clone(),
+        created: Some(created),
+        name: state.save_name.clone(),
+    };
+    match saves_dir().and_then(|dir| save(&game, &dir)) {
         Ok(filename) => {
+            // saving again updates this save, rather than making another
+            state.save_name = save_name_of(&filename);
             state.interface_state.status_text = Some((
                 Instant::now() + state.ui_settings.timings().status_display_secs,
                 format!("Saved to {:?}", filename),
@@ -173,8 +418,13 @@ pub fn handle_event(event: Event, state: &mut GameState) -> Result<bool> {
             return Ok(true);
         }
 
-        Event::MouseButtonDown { x, y, .. } => {
-            pick_up_cards(state, x, y);
+        Event::MouseButtonDown { x, y, clicks, .. } => {
+            // double-clicking finishes a game that's as good as won
+            if clicks == 2 && state.board.can_auto_complete() {
+                move_all_home(state);
+            } else {
+                pick_up_cards(state, x, y);
+            }
         }
 
         Event::MouseButtonUp { x, y, .. } => {
@@ -185,13 +435,52 @@ pub fn handle_event(event: Event, state: &mut GameState) -> Result<bool> {
             keycode: Some(key), ..
         } => match key {
             Keycode::Backspace => {
+                // undoing part-way through a sweep stops it
+                state.interface_state.moving_all_home = false;
                 state.board = state.undo_stack.undo(state.board.clone());
             }
             Keycode::Return => {
                 state.board = state.undo_stack.redo(state.board.clone());
             }
+            Keycode::B => {
+                switch_branch(state);
+            }
+            Keycode::J => {
+                state.interface_state.moving_all_home = false;
+                state.board = state.undo_stack.jump_to_branch_point(state.board.clone());
+            }
+            Keycode::L => {
+                list_branches(state);
+            }
             Keycode::C => {
-                copy_seed_to_clipboard(state);
+                if let Some(deal) = state.deal {
+                    copy_to_clipboard(state, deal.replay_text());
+                } else {
+                    state.interface_state.status_text = Some((
+                        Instant::now() + state.ui_settings.timings().status_display_secs,
+                        "No seed for a position, press X for its code".to_string(),
+                    ));
+                }
+            }
+            Keycode::X => {
+                copy_to_clipboard(state, state.board.to_code());
+            }
+            Keycode::H => {
+                show_hint(state);
+            }
+            Keycode::A => {
+                let policy = state.interface_state.auto_move_policy.next();
+                state.interface_state.auto_move_policy = policy;
+                state.interface_state.status_text = Some((
+                    Instant::now() + state.ui_settings.timings().status_display_secs,
+                    format!("Auto-move: {}", policy),
+                ));
+            }
+            Keycode::F => {
+                move_all_home(state);
+            }
+            Keycode::P => {
+                paste_moves(state);
             }
             Keycode::S => {
                 if !state.interface_state.s_key_held {
@@ -253,6 +542,13 @@ pub fn draw_canvas(state: &mut GameState, event_pump: &EventPump) -> Result<()>
         .into_canvas()
         .map_err(|s| anyhow!("creating canvas from surface: {}", s))?;
     let mouse = MouseState::new(&event_pump);
+    state.ui_settings.fit_to_board(state.board.view())?;
+
+    // only show the hint if the board hasn't changed since it was given
+    let hint = match &state.interface_state.hint {
+        Some((board, hint)) if board == &state.board => Some(hint),
+        _ => None,
+    };
 
     // Draw game to frame
     draw_board(
@@ -260,15 +556,12 @@ pub fn draw_canvas(state: &mut GameState, event_pump: &EventPump) -> Result<()>
         &state.board.view(),
         &state.ui_settings,
         (mouse.x(), mouse.y()),
+        hint,
     )?;
     if let Some((_, text)) = &state.interface_state.status_text {
         draw_status_text(&state.ui_settings, &mut frame, text)?;
     } else {
-        draw_status_text(
-            &state.ui_settings,
-            &mut frame,
-            &format!("seed: {}", state.seed),
-        )?;
+        draw_status_text(&state.ui_settings, &mut frame, &deal_text(state.deal))?;
     };
     draw_restart_message(&mut frame, state)?;
 
@@ -311,6 +604,20 @@ fn draw_restart_message(
     } else if state.board.view().is_won() {
         // otherwise, if the game is won, draw victory text
         draw_victory_text(&state.ui_settings, frame, "You Win!")?;
+    } else if let Some((hash, Some(dead_end))) = &state.interface_state.dead_end {
+        // otherwise, if the game can't be won anymore, say so
+        if *hash == state.board.position_hash() {
+            let text = match dead_end {
+                DeadEnd::NoMoves => "No Moves Left",
+                DeadEnd::Lost => "Can't Be Won",
+            };
+            draw_dead_end_text(
+                &state.ui_settings,
+                frame,
+                text,
+                "Undo with Backspace, or hold N for a new deal",
+            )?;
+        }
     }
     Ok(())
 }
@@ -28,10 +28,24 @@ use interface::*;
 ///
 /// Undo your previous move with `U` or `Backspace`.
 /// Redo an undone move with `R` or `Enter`.
+/// Making a different move after undoing starts a new branch, and the old moves are kept.
+/// Press `B` to switch to the next branch, `J` to jump back to where the last branch parts
+/// ways with this one, and `L` to list the branches.
 ///
+/// Press `H` to be shown a good next move.
+/// Press `A` to switch between auto-move policies: off, safe, or aggressive.
+/// Press `F` to move every card that can go to the foundations there, whatever the policy.
+/// Once no card is covering a lower one, `F` or a double-click finishes the game.
 /// Hold `N` to start a new game with a random seed.
-/// Press `S` to save your game.
-/// Press `C` to copy the game's seed to your clipboard.
+/// If you're playing a Microsoft deal, the new game will be a random Microsoft deal too.
+/// Press `S` to save your game. Saves are kept in the game's folder in your data directory
+/// (`$XDG_DATA_HOME/freecell/saves` or `~/.local/share/freecell/saves` on Linux), and are
+/// named after when they were made unless they're given a name with `--save-as`. Saving the
+/// same game again updates its save.
+/// Press `C` to copy the game's seed to your clipboard, or `--ms-deal` and its number for a
+/// Microsoft deal.
+/// Press `X` to copy a code for the current position, which can be played with `--position`.
+/// Press `P` to play the moves on your clipboard, written in standard notation (e.g. `3a 18 4h`).
 /// By loading from a seed, you can replay the same exact deal.
 #[derive(Clone, StructOpt)]
 #[structopt(name = "freecell", about = "FreeCell solitaire game")]
@@ -39,12 +53,38 @@ struct CliOptions {
     /// Seed to randomly generate game from
     #[structopt(short, long)]
     seed: Option<u64>,
-    /// Save file to load
+    /// Number of the Microsoft FreeCell deal to play
+    #[structopt(long, conflicts_with = "seed")]
+    ms_deal: Option<u32>,
+    /// Position code to play from, as copied with `X`
+    #[structopt(long, conflicts_with_all = &["seed", "ms_deal"])]
+    position: Option<String>,
+    /// Variant to play: freecell (the default), bakers-game, eight-off, seahaven, relaxed or
+    /// double-deck
+    #[structopt(long)]
+    variant: Option<Rules>,
+    /// Number of free cells to play with, instead of the variant's usual number
+    #[structopt(long)]
+    free_cells: Option<usize>,
+    /// Which cards move to the foundations on their own: off, safe or aggressive
+    #[structopt(long, default_value = "safe")]
+    auto_move: AutoMovePolicy,
+    /// Save to load, either by its name in the saves directory or by the path to its file
     #[structopt(short, long)]
     load: Option<PathBuf>,
+    /// Name to save the game under, replacing any earlier save with that name. Games after
+    /// this one are saved under the time they're saved, as usual
+    #[structopt(long)]
+    save_as: Option<String>,
+    /// List the saved games instead of playing
+    #[structopt(long)]
+    list_saves: bool,
     /// Output nothing to stdout or stderr
     #[structopt(short, long)]
     quiet: bool,
+    /// Print a solution for the game instead of playing it
+    #[structopt(long)]
+    solve: bool,
 }
 
 // holds the current state of the game
@@ -55,13 +95,25 @@ pub struct GameState<'a, 'b: 'a> {
     ui_settings: UiSettings<'a, 'b>,
     clipboard: Option<ClipboardContext>,
     canvas: Canvas<Window>,
-    seed: u64,
+    // how the game was dealt, or None for a game started from a position code
+    deal: Option<Deal>,
+    // when the game was first saved, if it has been
+    created: Option<u64>,
+    // the name the game is saved under, so that saving it again updates the same save
+    save_name: Option<String>,
     interface_state: InterfaceState,
 }
 
 fn main() -> Result<()> {
     let cli_options = CliOptions::from_args();
 
+    if cli_options.list_saves {
+        return print_saves();
+    }
+    if cli_options.solve {
+        return print_solution(&cli_options);
+    }
+
     // Build the window, canvas, and event pump
     let ttf_context = sdl2::ttf::init()?;
     let sdl_context = sdl2::init().unwrap();
@@ -109,48 +161,189 @@ fn initialize_state(
         None
     };
 
-    // Set up the UI
+    // Initialize the game state, either from a random seed or by loading a save file
+    let SavedGame {
+        deal,
+        board,
+        undo: undo_stack,
+        created,
+        name: save_name,
+    } = start_game(&opt)?;
+
+    // Set up the UI, laid out to fit the game's board
     let ui_settings = UiSettings::new(
         canvas.viewport().width(),
         canvas.viewport().height(),
         ttf_context,
+        BoardShape::of(board.view()),
     )?;
 
     // Initialize UI state
-    let interface_state = InterfaceState::new(&ui_settings);
+    let interface_state = InterfaceState::new(&ui_settings, opt.auto_move);
 
-    // Initialize the game state, either from a random seed or by loading a save file
-    let (seed, board, undo_stack) = if let Some(save_file_path) = &opt.load {
+    Ok(GameState {
+        opt,
+        canvas,
+        clipboard,
+        ui_settings,
+        board,
+        undo_stack,
+        interface_state,
+        deal,
+        created,
+        save_name,
+    })
+}
+
+// Get the game to play, either from a random seed or by loading a save file
+fn start_game(opt: &CliOptions) -> Result<SavedGame> {
+    let mut game = if let Some(save_name) = &opt.load {
+        let save_file_path = find_save(save_name)?;
         if !opt.quiet {
-            if opt.seed.is_some() {
+            if opt.seed.is_some() || opt.ms_deal.is_some() {
                 eprintln!("Ignoring seed in favour of loading from file");
             }
+            if opt.variant.is_some() || opt.free_cells.is_some() {
+                eprintln!("Ignoring variant in favour of the saved game's rules");
+            }
             eprintln!("Loading from {:?}", save_file_path);
         }
-        load(save_file_path)?
+        // a game from the saves directory is saved back to the same file
+        SavedGame {
+            name: save_name_of(&save_file_path),
+            ..load(&save_file_path)?
+        }
+    } else if let Some(code) = &opt.position {
+        // the position carries its own rules, and wasn't dealt from a seed
+        let board = Board::from_code(code).context("reading the position code")?;
+        if !opt.quiet {
+            if opt.variant.is_some() || opt.free_cells.is_some() {
+                eprintln!("Ignoring variant in favour of the position's rules");
+            }
+            eprintln!("Playing a position ({})", board.rules());
+        }
+        SavedGame {
+            deal: None,
+            board,
+            undo: BoardUndoStack::new(),
+            created: None,
+            name: None,
+        }
     } else {
+        let rules = game_rules(opt)?;
         // random seed
-        let seed = if let Some(s) = opt.seed {
-            s
+        let deal = if let Some(n) = opt.ms_deal {
+            // microsoft's deals are numbered from 1
+            if n == 0 {
+                return Err(anyhow!("there's no Microsoft deal 0; deals start at 1"));
+            }
+            Deal::Microsoft(n)
+        } else if let Some(s) = opt.seed {
+            Deal::Seed(s)
         } else {
-            rand::thread_rng().gen()
+            Deal::Seed(rand::thread_rng().gen())
         };
         if !opt.quiet {
-            eprintln!("Seed is {}", seed);
+            eprintln!("Playing {} ({})", deal, rules);
+        }
+        SavedGame {
+            deal: Some(deal),
+            board: deal.new_game(&rules)?,
+            undo: BoardUndoStack::new(),
+            created: None,
+            name: None,
         }
-        (seed, Board::new_game(seed), BoardUndoStack::new())
     };
+    if let Some(name) = &opt.save_as {
+        game.name = Some(name.clone());
+    }
+    Ok(game)
+}
 
-    Ok(GameState {
-        opt,
-        canvas,
-        clipboard,
-        ui_settings,
-        board,
-        undo_stack,
-        interface_state,
-        seed,
-    })
+// Get the rules picked on the command line
+fn game_rules(opt: &CliOptions) -> Result<Rules> {
+    let mut rules = opt.variant.unwrap_or_default();
+    if let Some(free_cells) = opt.free_cells {
+        // there's room for eight free cells on the board, and the dealt cards need somewhere to go
+        if free_cells < rules.dealt_to_free_cells || free_cells > MAX_FREE_CELLS {
+            return Err(anyhow!(
+                "{} needs between {} and {} free cells",
+                rules,
+                rules.dealt_to_free_cells,
+                MAX_FREE_CELLS
+            ));
+        }
+        rules.free_cells = free_cells;
+    }
+    Ok(rules)
+}
+
+// Print every saved game with its deal & how far along it is, most recent first
+fn print_saves() -> Result<()> {
+    let dir = saves_dir()?;
+    let saves = list_saves(&dir)?;
+    if saves.is_empty() {
+        println!("No saved games in {:?}", dir);
+    }
+    for save in saves {
+        let updated = format_time(save.updated);
+        match save.game {
+            Ok(game) => {
+                let view = game.board.view();
+                let home: usize = view.foundations.iter().map(|c| c.rank as usize).sum();
+                let progress = if view.is_won() {
+                    "won".to_string()
+                } else {
+                    format!("{}/{} cards home", home, 13 * view.foundations.len())
+                };
+                println!(
+                    "{:<24} {}  {}, {}, {} moves, {}",
+                    save.name,
+                    updated,
+                    deal_text(game.deal),
+                    game.board.rules(),
+                    game.undo.moves(),
+                    progress
+                );
+            }
+            Err(e) => println!("{:<24} {}  can't be loaded: {:#}", save.name, updated, e),
+        }
+    }
+    Ok(())
+}
+
+// How a game was dealt, for showing to the player
+pub fn deal_text(deal: Option<Deal>) -> String {
+    match deal {
+        Some(deal) => deal.to_string(),
+        None => "from a position".to_string(),
+    }
+}
+
+// Solve the game without opening a window, printing one move per line
+fn print_solution(opt: &CliOptions) -> Result<()> {
+    let board = start_game(opt)?.board;
+    match solve(&board, DEFAULT_SOLVER_BUDGET) {
+        SolveOutcome::Solved(moves) => {
+            // print the whole solution in standard notation, so it can be pasted into a game,
+            // then each move in standard notation followed by a description of it
+            println!("{}", format_moves(&board, &moves));
+            let mut board = board;
+            for (n, mv) in moves.iter().enumerate() {
+                println!("{:>3}. {:<4} {}", n + 1, format_move(&board, mv), mv);
+                board = board.apply(mv)?;
+            }
+        }
+        SolveOutcome::Unsolvable => println!("This game cannot be won"),
+        SolveOutcome::OutOfBudget => println!("Gave up looking for a solution"),
+        // a save or position code can have cards in the middle of being moved
+        SolveOutcome::Holding => {
+            return Err(anyhow!(
+                "can't solve a position where cards are being held; put them down first"
+            ))
+        }
+    }
+    Ok(())
 }
 
 // updates the state of the game; executed every step
@@ -158,16 +351,59 @@ fn update_game_state(state: &mut GameState) {
     // If we're not still on cooldown from the last auto-move
     if state.interface_state.next_auto_move <= Instant::now() {
         // try auto-moving another card to the foundations
-        if let Some(new_state) = state.board.auto_move_to_foundations() {
+        let policy = if state.interface_state.moving_all_home {
+            AutoMovePolicy::Aggressive
+        } else {
+            state.interface_state.auto_move_policy
+        };
+        if let Some(new_state) = state.board.auto_move_to_foundations(policy) {
             state.board = state
                 .undo_stack
                 .sneak_update(state.board.clone(), new_state);
-            // reset timeout
-            state.interface_state.next_auto_move =
-                Instant::now() + state.ui_settings.timings().auto_move_secs;
+            // reset timeout, which is shorter while sweeping every card home
+            let timings = state.ui_settings.timings();
+            state.interface_state.next_auto_move = Instant::now()
+                + if state.interface_state.moving_all_home {
+                    timings.auto_complete_secs
+                } else {
+                    timings.auto_move_secs
+                };
+        } else {
+            // nothing else can go home, so a one-off sweep is over
+            state.interface_state.moving_all_home = false;
         }
     }
 
+    // offer to finish the game once there's nothing left to decide
+    if state.board.can_auto_complete()
+        && !state.board.view().is_won()
+        && !state.interface_state.moving_all_home
+    {
+        if !state.interface_state.finish_offered {
+            state.interface_state.finish_offered = true;
+            state.interface_state.status_text = Some((
+                Instant::now() + state.ui_settings.timings().status_display_secs,
+                "Press F or double-click to finish".to_string(),
+            ));
+        }
+    } else {
+        state.interface_state.finish_offered = false;
+    }
+
+    // check whether the game has hit a dead end, once per position
+    let hash = state.board.position_hash();
+    let checked = match &state.interface_state.dead_end {
+        Some((checked_hash, _)) => *checked_hash == hash,
+        None => false,
+    };
+    if !checked && !state.board.has_floating() {
+        let dead_end = find_dead_end(&state.board, DEAD_END_SOLVER_BUDGET);
+        state.interface_state.dead_end = Some((hash, dead_end));
+    }
+
+    // show a hint if the search for one has finished
+    receive_hint(state);
+
     // Clear status text if it has expired
     if let Some((instant, _)) = state.interface_state.status_text {
         if instant < Instant::now() {
@@ -178,17 +414,33 @@ fn update_game_state(state: &mut GameState) {
     // if the player has been holding down "N" long enough, restart the game
     if let NewGameState::Starting(time) = state.interface_state.n_key_state {
         if time <= Instant::now() {
-            // restart game with new seed
-            let seed: u64 = thread_rng().gen();
-            state.seed = seed;
-            state.board = Board::new_game(seed);
-            state.undo_stack = BoardUndoStack::new();
+            // restart game with a new deal from the same generator, or a random seed after
+            // playing a position
+            let deal = match state.deal {
+                Some(deal) => deal.random_like(&mut thread_rng()),
+                None => Deal::Seed(thread_rng().gen()),
+            };
             state.interface_state.n_key_state = NewGameState::Cooldown;
-            state.interface_state.status_text = None;
-            state.interface_state.next_auto_move =
-                Instant::now() + state.ui_settings.timings().auto_move_secs;
-            if !state.opt.quiet {
-                eprintln!("Started new game. Seed is {}", seed);
+            match deal.new_game(state.board.rules()) {
+                Ok(board) => {
+                    state.deal = Some(deal);
+                    state.board = board;
+                    state.undo_stack = BoardUndoStack::new();
+                    state.created = None;
+                    state.save_name = None;
+                    state.interface_state.status_text = None;
+                    state.interface_state.next_auto_move =
+                        Instant::now() + state.ui_settings.timings().auto_move_secs;
+                    if !state.opt.quiet {
+                        eprintln!("Started new game. Playing {}", deal);
+                    }
+                }
+                Err(error) => {
+                    state.interface_state.status_text = Some((
+                        Instant::now() + state.ui_settings.timings().status_display_secs,
+                        format!("Can't start a new game: {}", error),
+                    ));
+                }
             }
         }
     }